@@ -0,0 +1,85 @@
+// Scanning short ranges against looking them up in the RMQ structures.
+// For each range length we time rmq() on every query, the scan that
+// rmq_many uses for short ranges (through smallest_in_range), and
+// rmq_many itself, over values that fit in the cache and values that
+// don't. Run with `cargo bench --bench rmq_many`, and with
+// RUSTFLAGS="-C target-cpu=native" to let the SIMD scan use AVX2.
+
+use idx::rmq::{smallest_in_range, BlockRMQImpl, PowerRMQImpl, RangeMinQuery};
+use idx::*;
+use std::hint::black_box;
+use std::ops::Range;
+use std::time::Instant;
+
+#[derive(Clone, Copy, Debug)]
+struct Values();
+impl SeqTrait for Values {
+    type Type = u32;
+}
+
+#[derive(Clone, Copy, Debug)]
+struct I {}
+impl TypeTrait for I {
+    type Type = u32;
+}
+impl CanIndex<Values> for I {}
+
+const QUERIES: usize = 1 << 18;
+
+// Nanoseconds per query
+fn time<F: FnMut() -> usize>(mut f: F) -> f64 {
+    let start = Instant::now();
+    black_box(f());
+    start.elapsed().as_nanos() as f64 / QUERIES as f64
+}
+
+fn bench<R: RangeMinQuery<Values>>(name: &str, rmq: &R, queries: &[Range<Val<I>>]) {
+    let x = rmq.values();
+    let lookup = time(|| {
+        queries
+            .iter()
+            .map(|r| rmq.rmq(r.clone()).idx().0 as usize)
+            .sum()
+    });
+    let scan = time(|| {
+        let points = queries
+            .iter()
+            .map(|r| smallest_in_range(x, r.clone()).unwrap());
+        points.map(|p| p.idx().0 as usize).sum()
+    });
+    let many = time(|| {
+        rmq.rmq_many(queries)
+            .iter()
+            .map(|p| p.idx().0 as usize)
+            .sum()
+    });
+    println!("{:>6} {:>10.1} {:>10.1} {:>10.1}", name, lookup, scan, many);
+}
+
+fn main() {
+    // A simple LCG, so the runs are the same every time
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut next = move || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (state >> 33) as usize
+    };
+    println!("ns per query: rmq(), scan, rmq_many()");
+    for n in [1 << 12, 1 << 22] {
+        let values: Vec<u32> = (0..n).map(|_| next() as u32).collect();
+        let power: PowerRMQImpl<Values> = PowerRMQImpl::new(values.clone().into());
+        let block: BlockRMQImpl<Values> = BlockRMQImpl::new(values.into());
+        for len in [8, 16, 32, 64, 128, 256] {
+            let queries: Vec<Range<Val<I>>> = (0..QUERIES)
+                .map(|_| {
+                    let i = next() % (n - len);
+                    Val(i as u32)..Val((i + len) as u32)
+                })
+                .collect();
+            println!("n {}, len {}", n, len);
+            bench("power", &power, &queries);
+            bench("block", &block, &queries);
+        }
+    }
+}
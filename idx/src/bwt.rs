@@ -0,0 +1,165 @@
+use crate::*;
+
+/// The Burrows-Wheeler transform of text, from its suffix array. The text
+/// is taken to end with a sentinel that is smaller than all characters, so
+/// the transform has a row for each of the n+1 suffixes of text$, in sorted
+/// order: row 0 is the sentinel suffix and row r+1 is the suffix at sa[r].
+/// Row r holds the character before its suffix, and the row for the whole
+/// text, which has no character before it, holds the sentinel.
+///
+/// The rows get their own index type, Row, since they are off by one from
+/// the suffix array positions and have nothing to do with text positions.
+/// We return the primary index, the row with the sentinel, as a Row. Since
+/// the primary index says where the sentinel is, the sentinel value you
+/// pass only fills in that row, and it doesn't have to be a character
+/// that is missing from the text.
+pub fn bwt<_Text, _Sa, _Bwt, Idx, Row>(
+    text: &IdxSlice<_Text>,
+    sa: &IdxSlice<_Sa>,
+    sentinel: _Text::Type,
+) -> (IdxVec<_Bwt>, Row)
+where
+    _Text: SeqTrait,
+    _Text::Type: Copy,
+    _Sa: SeqTrait<Type = Idx>,
+    _Bwt: SeqTrait<Type = _Text::Type>,
+    Idx: IndexType + CanIndex<_Text> + Copy,
+    Row: IndexType + CanIndex<_Bwt>,
+{
+    let x = text.as_slice();
+    let n = x.len();
+    assert_eq!(n, sa.len(), "the suffix array must have the text's length");
+
+    let mut b = Vec::with_capacity(n + 1);
+    let mut primary = 0;
+    // The sentinel suffix comes after the whole text
+    b.push(if n > 0 { x[n - 1] } else { sentinel });
+    for (r, &i) in sa.as_slice().iter().enumerate() {
+        match i.index() {
+            0 => {
+                primary = r + 1;
+                b.push(sentinel);
+            }
+            i => b.push(x[i - 1]),
+        }
+    }
+    (b.into(), Row::from_index(primary))
+}
+
+/// Recovers the text from its Burrows-Wheeler transform and primary
+/// index, as returned by bwt. We start in row 0, the sentinel suffix, whose
+/// character is the last in the text, and follow the LF mapping from each
+/// row to the row of the suffix that starts one character earlier. The
+/// characters are used as bucket indices, like in suffix_array.
+pub fn inverse_bwt<_Bwt, _Text, Row>(bwt: &IdxSlice<_Bwt>, primary: Row) -> IdxVec<_Text>
+where
+    _Bwt: SeqTrait,
+    _Bwt::Type: IndexType + Copy,
+    _Text: SeqTrait<Type = _Bwt::Type>,
+    Row: IndexType + CanIndex<_Bwt>,
+{
+    let b = bwt.as_slice();
+    assert!(!b.is_empty(), "a BWT has at least the sentinel row");
+    let primary = primary.index();
+    assert!(primary < b.len(), "primary index out of bounds");
+    let chars = || {
+        b.iter()
+            .enumerate()
+            .filter(move |&(r, _)| r != primary)
+            .map(|(_, c)| c.index())
+    };
+
+    // Rows starting with c come after the sentinel row and the rows with
+    // smaller characters, in the order of the rows they are in the BWT of.
+    let k = chars().max().map_or(0, |c| c + 1);
+    let mut next = vec![0; k];
+    for c in chars() {
+        next[c] += 1;
+    }
+    let mut sum = 1;
+    for c in next.iter_mut() {
+        (sum, *c) = (sum + *c, sum);
+    }
+    let mut lf = vec![0; b.len()];
+    for (r, c) in b.iter().enumerate() {
+        if r != primary {
+            lf[r] = next[c.index()];
+            next[c.index()] += 1;
+        }
+    }
+
+    let n = b.len() - 1;
+    let mut x = Vec::with_capacity(n);
+    let mut r = 0;
+    for _ in 0..n {
+        x.push(b[r]);
+        r = lf[r];
+    }
+    x.reverse();
+    x.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::suffix_array::suffix_array;
+
+    #[rustfmt::skip]
+    mod types {
+        use crate::*;
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Text();
+        impl SeqTrait for Text { type Type = u8; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct TextIdx {}
+        impl TypeTrait for TextIdx { type Type = u32; }
+        impl CanIndex<Text> for TextIdx {}
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct SA();
+        impl SeqTrait for SA { type Type = Val<TextIdx>; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Bwt();
+        impl SeqTrait for Bwt { type Type = u8; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Row {}
+        impl TypeTrait for Row { type Type = u32; }
+        impl CanIndex<Bwt> for Row {}
+    }
+    use types::*;
+
+    fn check_bwt(x: &[u8]) {
+        let text: IdxVec<Text> = x.to_vec().into();
+        let sa: IdxVec<SA> = suffix_array(&text);
+        let (b, primary): (IdxVec<Bwt>, Val<Row>) = bwt(&text, &sa, 0);
+        assert_eq!(x.len() + 1, b.len());
+        let back: IdxVec<Text> = inverse_bwt(&b, primary);
+        assert_eq!(x, back.0.as_slice());
+    }
+
+    #[test]
+    fn test_bwt() {
+        let text: IdxVec<Text> = b"banana".to_vec().into();
+        let sa: IdxVec<SA> = suffix_array(&text);
+        let (b, primary): (IdxVec<Bwt>, Val<Row>) = bwt(&text, &sa, b'$');
+        assert_eq!(b"annb$aa", b.0.as_slice());
+        assert_eq!(Val::<Row>(4), primary);
+        assert_eq!(b'$', b[primary]);
+
+        check_bwt(b"mississippi");
+        check_bwt(b"abracadabra");
+        check_bwt(b"aaaaaaa");
+        check_bwt(b"a");
+        check_bwt(b"");
+        // The sentinel value can appear in the text
+        check_bwt(&[0, 1, 0, 0, 2, 0]);
+        let x: Vec<u8> = (0..1000u32)
+            .map(|i| b"acgt"[(i * i % 7 + i / 3) as usize % 4])
+            .collect();
+        check_bwt(&x);
+    }
+}
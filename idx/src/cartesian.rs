@@ -0,0 +1,257 @@
+use crate::rmq::{right_wins, Order};
+use crate::*;
+
+/// Previous smaller values. For each position i in x, the largest j < i
+/// where x[j] is strictly smaller than x[i] according to order, or None
+/// if there is no such j. The result is a sequence of type _Out, indexed
+/// by the same index type as x. Linear time.
+pub fn previous_smaller<_Tag, _Out, Idx, O>(x: &IdxSlice<_Tag>, order: &O) -> IdxVec<_Out>
+where
+    _Tag: SeqTrait,
+    _Out: SeqTrait<Type = Option<Idx>>,
+    Idx: IndexType + CanIndex<_Tag> + Copy,
+    O: Order<_Tag::Type>,
+{
+    let x = x.as_slice();
+    let mut res = Vec::with_capacity(x.len());
+    let mut stack: Vec<usize> = Vec::with_capacity(x.len());
+    for i in 0..x.len() {
+        // Everything on the stack that isn't smaller than x[i] is hidden
+        // behind x[i] for the positions to the right of it.
+        while matches!(stack.last(), Some(&j) if order.cmp(&x[j], &x[i]).is_ge()) {
+            stack.pop();
+        }
+        res.push(stack.last().map(|&j| Idx::from_index(j)));
+        stack.push(i);
+    }
+    res.into()
+}
+
+/// Next smaller values. For each position i in x, the smallest j > i
+/// where x[j] is strictly smaller than x[i] according to order, or None
+/// if there is no such j. Linear time.
+pub fn next_smaller<_Tag, _Out, Idx, O>(x: &IdxSlice<_Tag>, order: &O) -> IdxVec<_Out>
+where
+    _Tag: SeqTrait,
+    _Out: SeqTrait<Type = Option<Idx>>,
+    Idx: IndexType + CanIndex<_Tag> + Copy,
+    O: Order<_Tag::Type>,
+{
+    let x = x.as_slice();
+    let mut res = vec![None; x.len()];
+    let mut stack: Vec<usize> = Vec::with_capacity(x.len());
+    for i in (0..x.len()).rev() {
+        while matches!(stack.last(), Some(&j) if order.cmp(&x[j], &x[i]).is_ge()) {
+            stack.pop();
+        }
+        res[i] = stack.last().map(|&j| Idx::from_index(j));
+        stack.push(i);
+    }
+    res.into()
+}
+
+/// The Cartesian tree of a sequence. The root is the first value in
+/// the sequence according to an order, and its left and right subtrees
+/// are the Cartesian trees of the values to the left and right of it.
+///
+/// Ties are broken the same way as in the RMQ structures, so the first
+/// value in x[i..=j] is the lowest common ancestor of i and j in the
+/// tree. The tree uses the same indices as the sequence, and the links
+/// are sequences of type _Tag, so the parent array can go straight into
+/// Lca.
+pub struct CartesianTree<_Tag: SeqTrait, Idx> {
+    root: Option<Idx>,
+    parents: IdxVec<_Tag>,
+    left: IdxVec<_Tag>,
+    right: IdxVec<_Tag>,
+}
+
+impl<_Tag, Idx> CartesianTree<_Tag, Idx>
+where
+    _Tag: SeqTrait<Type = Option<Idx>>,
+    Idx: IndexType + CanIndex<_Tag> + Copy,
+{
+    /// Build the tree for x in linear time.
+    pub fn new<_Seq, O>(x: &IdxSlice<_Seq>, order: &O) -> CartesianTree<_Tag, Idx>
+    where
+        _Seq: SeqTrait,
+        Idx: CanIndex<_Seq>,
+        O: Order<_Seq::Type>,
+    {
+        let x = x.as_slice();
+        let n = x.len();
+        let (mut parents, mut left, mut right) = (vec![None; n], vec![None; n], vec![None; n]);
+
+        // The stack holds the right-most path of the tree for x[..i].
+        // The values x[i] wins over become its left subtree, and x[i]
+        // becomes the right child of what is left on the stack.
+        let mut stack: Vec<usize> = Vec::with_capacity(n);
+        for i in 0..n {
+            let mut last = None;
+            while matches!(stack.last(), Some(&j) if right_wins(order, &x[j], &x[i])) {
+                last = stack.pop();
+            }
+            if let Some(j) = last {
+                left[i] = Some(Idx::from_index(j));
+                parents[j] = Some(Idx::from_index(i));
+            }
+            if let Some(&j) = stack.last() {
+                right[j] = Some(Idx::from_index(i));
+                parents[i] = Some(Idx::from_index(j));
+            }
+            stack.push(i);
+        }
+
+        CartesianTree {
+            root: stack.first().map(|&i| Idx::from_index(i)),
+            parents: parents.into(),
+            left: left.into(),
+            right: right.into(),
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty()
+    }
+
+    /// The root of the tree, or None if the sequence is empty.
+    #[inline]
+    pub fn root(&self) -> Option<Idx> {
+        self.root
+    }
+
+    #[inline]
+    pub fn parent(&self, i: Idx) -> Option<Idx> {
+        self.parents[i]
+    }
+
+    #[inline]
+    pub fn left(&self, i: Idx) -> Option<Idx> {
+        self.left[i]
+    }
+
+    #[inline]
+    pub fn right(&self, i: Idx) -> Option<Idx> {
+        self.right[i]
+    }
+
+    /// The parent of every node, with None for the root.
+    #[inline]
+    pub fn parents(&self) -> &IdxSlice<_Tag> {
+        &self.parents
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lca::Lca;
+    use crate::rmq::{Largest, NaiveRMQImpl, RangeMinQuery, Rightmost, Smallest};
+
+    #[rustfmt::skip]
+    mod types {
+        use crate::*;
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Xs();
+        impl SeqTrait for Xs { type Type = u32; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Links();
+        impl SeqTrait for Links { type Type = Option<Val<I>>; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct I {}
+        impl TypeTrait for I { type Type = u32; }
+        impl CanIndex<Xs> for I {}
+        impl CanIndex<Links> for I {}
+    }
+    use types::*;
+
+    fn idx(i: usize) -> Val<I> {
+        i.into()
+    }
+
+    fn check_nearest<O: Order<u32>>(v: &[u32], order: O) {
+        let x: IdxVec<Xs> = v.to_vec().into();
+        let psv: IdxVec<Links> = previous_smaller(&x, &order);
+        let nsv: IdxVec<Links> = next_smaller(&x, &order);
+        let smaller = |j: &usize, i: usize| order.cmp(&v[*j], &v[i]).is_lt();
+        for i in 0..v.len() {
+            let p = (0..i).rev().find(|j| smaller(j, i)).map(idx);
+            let n = (i + 1..v.len()).find(|j| smaller(j, i)).map(idx);
+            assert_eq!(p, psv[idx(i)]);
+            assert_eq!(n, nsv[idx(i)]);
+        }
+    }
+
+    #[test]
+    fn test_nearest_smaller() {
+        let v = vec![3, 1, 4, 1, 5, 9, 2, 6, 5, 3, 5];
+        check_nearest(&v, Smallest);
+        check_nearest(&v, Largest);
+        check_nearest(&[2, 2, 2, 1, 1], Smallest);
+        check_nearest(&[], Smallest);
+
+        let x: IdxVec<Xs> = vec![2, 0, 1].into();
+        let psv: IdxVec<Links> = previous_smaller(&x, &Smallest);
+        assert_eq!(vec![None, None, Some(idx(1))], psv.0);
+    }
+
+    fn check_tree<O: Order<u32> + Clone>(v: &[u32], order: O) {
+        let x: IdxVec<Xs> = v.to_vec().into();
+        let tree: CartesianTree<Links, Val<I>> = CartesianTree::new(&x, &order);
+        assert_eq!(v.len(), tree.len());
+        if v.is_empty() {
+            assert_eq!(None, tree.root());
+            return;
+        }
+
+        // Links are consistent and the tree is in-order over positions
+        for i in 0..v.len() {
+            if let Some(l) = tree.left(idx(i)) {
+                assert!(l < idx(i));
+                assert_eq!(Some(idx(i)), tree.parent(l));
+            }
+            if let Some(r) = tree.right(idx(i)) {
+                assert!(r > idx(i));
+                assert_eq!(Some(idx(i)), tree.parent(r));
+            }
+        }
+
+        // The lowest common ancestor of i and j is the first in x[i..=j]
+        let rmq = NaiveRMQImpl::<Xs, O>::build(x, order);
+        let lca: Lca<Links, Val<I>> = Lca::new(tree.parents());
+        assert_eq!(rmq.rmq(idx(0)..idx(v.len())).idx(), tree.root().unwrap());
+        for i in 0..v.len() {
+            for j in i..v.len() {
+                assert_eq!(rmq.rmq(idx(i)..idx(j + 1)).idx(), lca.lca(idx(i), idx(j)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_cartesian_tree() {
+        let v = vec![3, 1, 4, 1, 5, 9, 2, 6, 5, 3, 5];
+        check_tree(&v, Smallest);
+        check_tree(&v, Largest);
+        check_tree(&v, Rightmost(Smallest));
+        check_tree(&[7, 7, 7, 7], Smallest);
+        check_tree(&[7, 7, 7, 7], Rightmost(Smallest));
+        check_tree(&[1], Smallest);
+        check_tree(&[], Smallest);
+
+        let x: IdxVec<Xs> = vec![2, 0, 1].into();
+        let tree: CartesianTree<Links, Val<I>> = CartesianTree::new(&x, &Smallest);
+        assert_eq!(Some(idx(1)), tree.root());
+        assert_eq!(Some(idx(0)), tree.left(idx(1)));
+        assert_eq!(Some(idx(2)), tree.right(idx(1)));
+        assert_eq!(None, tree.left(idx(0)));
+    }
+}
@@ -0,0 +1,392 @@
+use crate::*;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+// Undefined entries in the child table
+const NONE: usize = usize::MAX;
+
+/// An lcp-interval: the suffix array range of the suffixes that share a
+/// prefix of length lcp, and no longer one, together with its child
+/// intervals. The children partition the range, and singleton children
+/// are the leaves. In suffix tree terms, it is an internal node at string
+/// depth lcp.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LcpInterval<SaIdx, Len> {
+    pub lcp: Len,
+    pub range: Range<SaIdx>,
+    pub children: Vec<Range<SaIdx>>,
+}
+
+/// Enhanced suffix array: the LCP array of a suffix array with the child
+/// table of Abouelhoda, Kurtz and Ohlebusch, which lets us traverse the
+/// lcp-intervals top-down like the nodes of a suffix tree. Bottom-up
+/// traversals only need the LCP array and a stack.
+///
+/// The LCP array is the one from crate::lcp, where lcp[r] is the common
+/// prefix of the suffixes at r-1 and r, indexed by the suffix array's
+/// index type, SaIdx. All ranges are in SaIdx.
+pub struct EnhancedSuffixArray<_Lcp: SeqTrait, SaIdx> {
+    lcp: IdxVec<_Lcp>,
+    // up[k] and down[k] are the first l-index of the intervals that end
+    // right before k or start at k, and next_l[k] is the next l-index
+    // after k in the same interval.
+    up: Vec<usize>,
+    down: Vec<usize>,
+    next_l: Vec<usize>,
+    _index: PhantomData<SaIdx>,
+}
+
+impl<_Lcp, SaIdx, Len> EnhancedSuffixArray<_Lcp, SaIdx>
+where
+    _Lcp: SeqTrait<Type = Len>,
+    Len: IndexType + Copy,
+    SaIdx: IndexType + CanIndex<_Lcp> + Copy,
+{
+    /// Builds the child table over the LCP array, in linear time.
+    pub fn new(lcp: IdxVec<_Lcp>) -> EnhancedSuffixArray<_Lcp, SaIdx> {
+        let n = lcp.len();
+        let mut esa = EnhancedSuffixArray {
+            lcp,
+            up: vec![],
+            down: vec![],
+            next_l: vec![],
+            _index: PhantomData,
+        };
+        let (mut up, mut down, mut next_l) =
+            (vec![NONE; n + 1], vec![NONE; n + 1], vec![NONE; n + 1]);
+        let at = |k: usize| esa.lcp_at(k);
+
+        // up and down
+        let mut stack = vec![0];
+        let mut last = NONE;
+        for (k, up) in up.iter_mut().enumerate().skip(1) {
+            while at(k) < at(*stack.last().unwrap()) {
+                last = stack.pop().unwrap();
+                let top = *stack.last().unwrap();
+                if at(k) <= at(top) && at(top) != at(last) {
+                    down[top] = last;
+                }
+            }
+            // The last interval we closed ends right before k
+            *up = std::mem::replace(&mut last, NONE);
+            stack.push(k);
+        }
+
+        // next_l
+        let mut stack = vec![0];
+        for k in 1..=n {
+            while at(k) < at(*stack.last().unwrap()) {
+                stack.pop();
+            }
+            if at(k) == at(*stack.last().unwrap()) {
+                next_l[stack.pop().unwrap()] = k;
+            }
+            stack.push(k);
+        }
+        (esa.up, esa.down, esa.next_l) = (up, down, next_l);
+        esa
+    }
+
+    // The LCP array with None, below all lengths, at both ends
+    #[inline]
+    fn lcp_at(&self, k: usize) -> Option<usize> {
+        match k {
+            0 => None,
+            k if k == self.len() => None,
+            k => Some(self.lcp.as_slice()[k].index()),
+        }
+    }
+
+    /// The number of suffixes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.lcp.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.lcp.is_empty()
+    }
+
+    #[inline]
+    pub fn lcp(&self) -> &IdxSlice<_Lcp> {
+        &self.lcp
+    }
+
+    /// The interval of all suffixes, the root of the suffix tree.
+    pub fn root(&self) -> Range<SaIdx> {
+        SaIdx::from_index(0)..SaIdx::from_index(self.len())
+    }
+
+    // The first l-index of the interval [i,j), or NONE for a singleton
+    fn first_l_index(&self, i: usize, j: usize) -> usize {
+        if j - i < 2 {
+            return NONE;
+        }
+        match self.up[j] {
+            k if k != NONE && i < k && k < j => k,
+            _ => self.down[i],
+        }
+    }
+
+    /// The length of the prefix shared by the suffixes in the lcp-interval
+    /// r. For a singleton, that's the whole suffix, which we don't know
+    /// without the text, so r must have at least two suffixes.
+    pub fn interval_lcp(&self, r: Range<SaIdx>) -> Len {
+        let (i, j) = (r.start.index(), r.end.index());
+        assert!(i + 1 < j && j <= self.len(), "not an lcp-interval");
+        self.lcp.as_slice()[self.first_l_index(i, j)]
+    }
+
+    /// The child intervals of the lcp-interval r, from the child table, in
+    /// constant time per child. A singleton has no children. The result is
+    /// meaningless if r isn't an lcp-interval, such as the root or a
+    /// range from lcp_intervals or children.
+    pub fn children(&self, r: Range<SaIdx>) -> Vec<Range<SaIdx>> {
+        let (i, j) = (r.start.index(), r.end.index());
+        assert!(i <= j && j <= self.len(), "range out of bounds");
+        let mut bounds = vec![];
+        let mut k = self.first_l_index(i, j);
+        while k != NONE && k < j {
+            bounds.push(k);
+            k = self.next_l[k];
+        }
+        split(i, j, &bounds)
+    }
+
+    /// All lcp-intervals in bottom-up order, so each interval comes after
+    /// its children, with the root last. We sweep over the LCP array once,
+    /// lazily, with our own stack of the open intervals: an interval closes
+    /// at the first smaller LCP value after it, and its children are split
+    /// at the positions where the LCP equals its own.
+    pub fn lcp_intervals(&self) -> impl Iterator<Item = LcpInterval<SaIdx, Len>> + '_ {
+        LcpIntervals {
+            esa: self,
+            k: 1,
+            stack: match self.len() {
+                0 => vec![],
+                _ => vec![Open {
+                    lcp: 0,
+                    start: 0,
+                    bounds: vec![],
+                }],
+            },
+            ready: VecDeque::new(),
+        }
+    }
+}
+
+// The ranges [i,b1), [b1,b2), ..., [bk,j), or none if there are no bounds
+fn split<SaIdx: IndexType>(i: usize, j: usize, bounds: &[usize]) -> Vec<Range<SaIdx>> {
+    if bounds.is_empty() {
+        return vec![];
+    }
+    let starts = std::iter::once(i).chain(bounds.iter().copied());
+    let ends = bounds.iter().copied().chain(std::iter::once(j));
+    starts
+        .zip(ends)
+        .map(|(s, e)| SaIdx::from_index(s)..SaIdx::from_index(e))
+        .collect()
+}
+
+// An interval we haven't seen the end of yet
+struct Open {
+    lcp: usize,
+    start: usize,
+    // The l-indices so far
+    bounds: Vec<usize>,
+}
+
+struct LcpIntervals<'a, _Lcp: SeqTrait, SaIdx> {
+    esa: &'a EnhancedSuffixArray<_Lcp, SaIdx>,
+    // The next LCP position to look at
+    k: usize,
+    stack: Vec<Open>,
+    // Intervals we have closed but not returned yet
+    ready: VecDeque<(Open, usize)>,
+}
+
+impl<_Lcp, SaIdx, Len> Iterator for LcpIntervals<'_, _Lcp, SaIdx>
+where
+    _Lcp: SeqTrait<Type = Len>,
+    Len: IndexType + Copy,
+    SaIdx: IndexType + CanIndex<_Lcp> + Copy,
+{
+    type Item = LcpInterval<SaIdx, Len>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let n = self.esa.len();
+        while self.ready.is_empty() && self.k <= n {
+            let (k, l) = (self.k, self.esa.lcp_at(self.k));
+            let mut start = k - 1;
+            while matches!(self.stack.last(), Some(top) if l < Some(top.lcp)) {
+                let open = self.stack.pop().unwrap();
+                start = open.start;
+                // Without l-indices, it's the root we started with, and
+                // either all the suffixes share a prefix, so we just
+                // closed the real root, or there is only one suffix.
+                if !open.bounds.is_empty() {
+                    self.ready.push_back((open, k));
+                }
+            }
+            match (self.stack.last_mut(), l) {
+                (Some(top), Some(l)) if top.lcp == l => top.bounds.push(k),
+                (_, Some(l)) => self.stack.push(Open {
+                    lcp: l,
+                    start,
+                    bounds: vec![k],
+                }),
+                (_, None) => {}
+            }
+            self.k += 1;
+        }
+        let (open, end) = self.ready.pop_front()?;
+        Some(LcpInterval {
+            lcp: Len::from_index(open.lcp),
+            range: SaIdx::from_index(open.start)..SaIdx::from_index(end),
+            children: split(open.start, end, &open.bounds),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lcp::lcp_kasai;
+    use crate::suffix_array::suffix_array;
+
+    #[rustfmt::skip]
+    mod types {
+        use crate::*;
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Text();
+        impl SeqTrait for Text { type Type = u8; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct TextIdx {}
+        impl TypeTrait for TextIdx { type Type = u32; }
+        impl CanIndex<Text> for TextIdx {}
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Offset {}
+        impl TypeTrait for Offset { type Type = u32; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct SA();
+        impl SeqTrait for SA { type Type = Val<TextIdx>; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Lcp();
+        impl SeqTrait for Lcp { type Type = Val<Offset>; }
+
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub struct SaIdx {}
+        impl TypeTrait for SaIdx { type Type = u32; }
+        impl CanIndex<SA> for SaIdx {}
+        impl CanIndex<Lcp> for SaIdx {}
+    }
+    use types::*;
+
+    type Esa = EnhancedSuffixArray<Lcp, Val<SaIdx>>;
+
+    fn esa(x: &[u8]) -> Esa {
+        let text: IdxVec<Text> = x.to_vec().into();
+        let sa: IdxVec<SA> = suffix_array(&text);
+        Esa::new(lcp_kasai(&text, &sa))
+    }
+
+    fn raw(r: &Range<Val<SaIdx>>) -> (usize, usize) {
+        (r.start.index(), r.end.index())
+    }
+
+    // All lcp-intervals [i,j) with at least two suffixes, straight from
+    // the definition
+    fn naive_intervals(lcp: &[usize]) -> Vec<(usize, usize, usize)> {
+        let n = lcp.len();
+        let at = |k: usize| if k == 0 || k == n { None } else { Some(lcp[k]) };
+        let mut res = vec![];
+        for i in 0..n {
+            for j in i + 2..=n {
+                let l = (i + 1..j).map(|k| lcp[k]).min().unwrap();
+                if at(i) < Some(l) && at(j) < Some(l) {
+                    res.push((l, i, j));
+                }
+            }
+        }
+        res.sort();
+        res
+    }
+
+    fn check_esa(x: &[u8]) {
+        let esa = esa(x);
+        let lcp: Vec<usize> = esa.lcp().as_slice().iter().map(|l| l.index()).collect();
+        let intervals: Vec<_> = esa.lcp_intervals().collect();
+
+        // Children come before their parents
+        for (p, parent) in intervals.iter().enumerate() {
+            for child in &parent.children {
+                let (i, j) = raw(child);
+                if j - i > 1 {
+                    let c = intervals
+                        .iter()
+                        .position(|c| raw(&c.range) == (i, j))
+                        .unwrap();
+                    assert!(c < p);
+                }
+            }
+        }
+        if let Some(root) = intervals.last() {
+            assert_eq!(esa.root(), root.range);
+        }
+
+        // The same intervals as the definition, apart from the root of a
+        // single suffix, which is a leaf
+        let mut found: Vec<_> = intervals
+            .iter()
+            .filter(|iv| iv.range.end.index() - iv.range.start.index() > 1)
+            .map(|iv| (iv.lcp.index(), iv.range.start.index(), iv.range.end.index()))
+            .collect();
+        found.sort();
+        assert_eq!(naive_intervals(&lcp), found);
+
+        // Top-down with the child table agrees with bottom-up
+        for iv in intervals.iter().filter(|iv| !iv.children.is_empty()) {
+            assert_eq!(iv.children, esa.children(iv.range.clone()));
+            assert_eq!(iv.lcp, esa.interval_lcp(iv.range.clone()));
+        }
+    }
+
+    #[test]
+    fn test_esa() {
+        check_esa(b"mississippi");
+        check_esa(b"abracadabra");
+        check_esa(b"aaaaaaa");
+        check_esa(b"abababab");
+        check_esa(b"ab");
+        check_esa(b"a");
+        check_esa(b"");
+        let x: Vec<u8> = (0..200u32)
+            .map(|i| b"acgt"[(i * i % 7 + i / 3) as usize % 4])
+            .collect();
+        check_esa(&x);
+    }
+
+    #[test]
+    fn test_typed_intervals() {
+        // Suffixes of mississippi: i ippi issippi ississippi mississippi
+        // pi ppi sippi sissippi ssippi ssissippi
+        let esa = esa(b"mississippi");
+        let r = |i: usize, j: usize| Val::<SaIdx>::from(i)..Val::<SaIdx>::from(j);
+        let root = esa.children(esa.root());
+        assert_eq!(vec![r(0, 4), r(4, 5), r(5, 7), r(7, 11)], root);
+        assert_eq!(vec![r(0, 1), r(1, 2), r(2, 4)], esa.children(r(0, 4)));
+        assert_eq!(Val::<Offset>(4), esa.interval_lcp(r(2, 4)));
+        assert!(esa.children(r(4, 5)).is_empty());
+
+        let first = esa.lcp_intervals().next().unwrap();
+        assert_eq!(Val::<Offset>(4), first.lcp);
+        assert_eq!(r(2, 4), first.range);
+    }
+}
@@ -0,0 +1,225 @@
+use crate::*;
+use num::Zero;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+/// Fenwick (binary indexed) tree for prefix sums. It represents a
+/// sequence of n values of type Val<S>, all zero to begin with, and
+/// positions into it are any index type that can index _Tag. With it
+/// we can add to a value and get the sum of a prefix or a range, all
+/// in O(log n).
+///
+/// Internally, node i (1-indexed) holds the sum of the values in
+/// (i - lsb(i), i], where lsb(i) is the lowest set bit in i.
+pub struct FenwickTree<_Tag: SeqTrait, S: TypeTrait> {
+    tree: Vec<S::Type>,
+    _tag: PhantomData<_Tag>,
+}
+
+// Lowest set bit
+#[inline]
+fn lsb(i: usize) -> usize {
+    i & i.wrapping_neg()
+}
+
+impl<_Tag, S> FenwickTree<_Tag, S>
+where
+    _Tag: SeqTrait,
+    S: TypeTrait,
+{
+    /// A tree over n values, all zero.
+    pub fn new(n: usize) -> FenwickTree<_Tag, S> {
+        FenwickTree {
+            tree: vec![S::Type::zero(); n + 1],
+            _tag: PhantomData,
+        }
+    }
+
+    /// A tree over a sequence of values, built in O(n).
+    pub fn from_values(values: &IdxSlice<_Tag>) -> FenwickTree<_Tag, S>
+    where
+        _Tag: SeqTrait<Type = Val<S>>,
+    {
+        let n = values.len();
+        let mut tree = vec![S::Type::zero(); n + 1];
+        for (i, x) in values.as_slice().iter().enumerate() {
+            tree[i + 1] = x.0;
+        }
+        // Push each node's sum to its parent, which covers it.
+        for i in 1..=n {
+            let parent = i + lsb(i);
+            if parent <= n {
+                tree[parent] = tree[parent] + tree[i];
+            }
+        }
+        FenwickTree {
+            tree,
+            _tag: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.tree.len() - 1
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Add delta to the value at position i.
+    pub fn add<Idx>(&mut self, i: Idx, delta: Val<S>)
+    where
+        Idx: IndexType + CanIndex<_Tag> + Copy,
+    {
+        let mut i = i.index() + 1;
+        assert!(i <= self.len(), "index out of bounds");
+        while i <= self.len() {
+            self.tree[i] = self.tree[i] + delta.0;
+            i += lsb(i);
+        }
+    }
+
+    /// The sum of the values in [0,i).
+    pub fn prefix<Idx>(&self, i: Idx) -> Val<S>
+    where
+        Idx: IndexType + CanIndex<_Tag> + Copy,
+    {
+        let mut i = i.index();
+        assert!(i <= self.len(), "index out of bounds");
+        let mut sum = S::Type::zero();
+        while i > 0 {
+            sum = sum + self.tree[i];
+            i -= lsb(i);
+        }
+        Val(sum)
+    }
+
+    /// The sum of the values in the range r.
+    pub fn range_sum<Idx>(&self, r: Range<Idx>) -> Val<S>
+    where
+        Idx: IndexType + CanIndex<_Tag> + Copy,
+    {
+        assert!(r.start.index() <= r.end.index(), "invalid range");
+        Val(self.prefix(r.end).0 - self.prefix(r.start).0)
+    }
+
+    /// The smallest position i such that the sum of [0,i) is at least
+    /// target, or None if not even the sum of the whole sequence is. Then
+    /// Some(n) means that only the full prefix [0,n) reaches target. This
+    /// only makes sense when all the values are non-negative, so the
+    /// prefix sums are sorted.
+    pub fn lower_bound<Idx>(&self, target: Val<S>) -> Option<Idx>
+    where
+        Idx: IndexType + CanIndex<_Tag> + Copy,
+    {
+        let n = self.len();
+        if target.0 <= S::Type::zero() {
+            return Some(Idx::from_index(0));
+        }
+        // Walk down the implicit tree, finding the largest pos where
+        // the sum of [0,pos) is less than target.
+        let (mut pos, mut rest) = (0, target.0);
+        let mut step = if n == 0 { 0 } else { 1 << n.ilog2() };
+        while step > 0 {
+            if pos + step <= n && self.tree[pos + step] < rest {
+                pos += step;
+                rest = rest - self.tree[pos];
+            }
+            step >>= 1;
+        }
+        // The sum of [0,pos+1) is at least target, unless we ran off the end.
+        (pos < n).then(|| Idx::from_index(pos + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rustfmt::skip]
+    mod types {
+        use crate::*;
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Len {}
+        impl TypeTrait for Len { type Type = i64; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Lens();
+        impl SeqTrait for Lens { type Type = Val<Len>; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct I {}
+        impl TypeTrait for I { type Type = u32; }
+        impl CanIndex<Lens> for I {}
+    }
+    use types::*;
+
+    fn idx(i: usize) -> Val<I> {
+        i.into()
+    }
+    fn len(x: i64) -> Val<Len> {
+        Val(x)
+    }
+
+    #[test]
+    fn test_prefix_sums() {
+        let mut v: Vec<i64> = vec![3, 0, 5, 1, 1, 4, 0, 2, 6, 3, 1];
+        let n = v.len();
+        let lens: IdxVec<Lens> = v.iter().map(|&x| len(x)).collect::<Vec<_>>().into();
+        let mut tree: FenwickTree<Lens, Len> = FenwickTree::from_values(&lens);
+        assert_eq!(n, tree.len());
+
+        let check = |v: &[i64], tree: &FenwickTree<Lens, Len>| {
+            for i in 0..n + 1 {
+                assert_eq!(len(v[..i].iter().sum()), tree.prefix(idx(i)));
+                for j in i..n + 1 {
+                    assert_eq!(len(v[i..j].iter().sum()), tree.range_sum(idx(i)..idx(j)));
+                }
+            }
+        };
+        check(&v, &tree);
+
+        for (i, d) in [(0, 2), (5, -4), (10, 7), (3, 1), (7, 0)] {
+            v[i] += d;
+            tree.add(idx(i), len(d));
+            check(&v, &tree);
+        }
+
+        // Building with add gives the same tree as building from the values
+        let mut tree2: FenwickTree<Lens, Len> = FenwickTree::new(n);
+        for (i, &x) in v.iter().enumerate() {
+            tree2.add(idx(i), len(x));
+        }
+        check(&v, &tree2);
+    }
+
+    #[test]
+    fn test_lower_bound() {
+        let v: Vec<i64> = vec![2, 0, 3, 1, 0, 0, 4];
+        let lens: IdxVec<Lens> = v.iter().map(|&x| len(x)).collect::<Vec<_>>().into();
+        let tree: FenwickTree<Lens, Len> = FenwickTree::from_values(&lens);
+        let total: i64 = v.iter().sum();
+        for target in -1..total + 3 {
+            // Naive: first i with sum of [0,i) >= target
+            let expected = (0..=v.len()).find(|&i| v[..i].iter().sum::<i64>() >= target);
+            let i: Option<Val<I>> = tree.lower_bound(len(target));
+            assert_eq!(expected.map(idx), i);
+        }
+
+        // Only the full prefix reaches the total, and nothing reaches past it
+        let i: Option<Val<I>> = tree.lower_bound(len(total));
+        assert_eq!(Some(idx(v.len())), i);
+        let i: Option<Val<I>> = tree.lower_bound(len(total + 1));
+        assert_eq!(None, i);
+
+        let empty: FenwickTree<Lens, Len> = FenwickTree::new(0);
+        assert!(empty.is_empty());
+        let i: Option<Val<I>> = empty.lower_bound(len(1));
+        assert_eq!(None, i);
+        let i: Option<Val<I>> = empty.lower_bound(len(0));
+        assert_eq!(Some(idx(0)), i);
+    }
+}
@@ -0,0 +1,334 @@
+use crate::bwt::bwt;
+use crate::wavelet::{RankBits, WaveletCodes};
+use crate::*;
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+// The BWT we search in, as a sequence indexed by plain rows
+struct BwtChars<T>(PhantomData<T>);
+impl<T> SeqTrait for BwtChars<T> {
+    type Type = T;
+}
+impl<T> CanIndex<BwtChars<T>> for usize {}
+
+/// FM-index over a text: the Burrows-Wheeler transform with a rank
+/// structure on top, for counting the occurrences of a pattern without
+/// the text or the suffix array, and a sample of the suffix array for
+/// locating them.
+///
+/// Backward search gives the occurrences as a range of suffix array
+/// positions, SaIdx, and locate turns those into text positions, TextIdx,
+/// so the two kinds of positions can't be mixed up. Internally we work
+/// on BWT rows, which are suffix array positions plus one, because of the
+/// sentinel row at the top.
+///
+/// We only keep the characters that occur in the text, so with σ of them
+/// the BWT is a wavelet matrix of ceil(log2 σ) bits per character, plus an
+/// eighth for rank. Marking the sampled rows costs another 1.125 bits per
+/// character, and the samples themselves one TextIdx per sample_rate
+/// characters. On top of that there are two words per distinct character.
+/// Each LF step, and so each pattern character in backward search and
+/// each step in locate, takes O(log σ) time.
+pub struct FmIndex<_Text: SeqTrait, SaIdx, TextIdx> {
+    // The distinct characters in the text, sorted; a character's code is
+    // its position here
+    alphabet: Vec<usize>,
+    // c_tab[a] is the first row whose suffix starts with the character
+    // with code a
+    c_tab: Vec<usize>,
+    // The codes of the BWT characters, without the sentinel's row
+    wm: WaveletCodes,
+    // The row that holds the sentinel
+    primary: usize,
+    len: usize,
+    sample_rate: usize,
+    // Rows whose suffix starts at a multiple of the sample rate
+    sampled: RankBits,
+    // The text positions of the sampled rows, in row order
+    samples: Vec<TextIdx>,
+    _index: PhantomData<fn(&_Text, SaIdx) -> TextIdx>,
+}
+
+impl<_Text, SaIdx, TextIdx> FmIndex<_Text, SaIdx, TextIdx>
+where
+    _Text: SeqTrait,
+    _Text::Type: IndexType + Copy,
+    SaIdx: IndexType + Copy,
+    TextIdx: IndexType + CanIndex<_Text> + Copy,
+{
+    /// Builds the index from the text and its suffix array, keeping the
+    /// suffix array entries for every sample_rate'th text position.
+    /// Locating an occurrence takes up to sample_rate - 1 LF steps.
+    pub fn new<_Sa>(
+        text: &IdxSlice<_Text>,
+        sa: &IdxSlice<_Sa>,
+        sample_rate: usize,
+    ) -> FmIndex<_Text, SaIdx, TextIdx>
+    where
+        _Sa: SeqTrait<Type = TextIdx>,
+        SaIdx: CanIndex<_Sa>,
+    {
+        assert!(sample_rate > 0, "the sample rate must be positive");
+        let (b, primary): (IdxVec<BwtChars<_Text::Type>>, usize) =
+            bwt(text, sa, _Text::Type::from_index(0));
+        let rows = b.len();
+        let chars = || {
+            b.as_slice()
+                .iter()
+                .enumerate()
+                .filter(move |&(r, _)| r != primary)
+                .map(|(_, c)| c.index())
+        };
+
+        let mut counts = BTreeMap::new();
+        for c in chars() {
+            *counts.entry(c).or_insert(0) += 1;
+        }
+        let alphabet: Vec<usize> = counts.keys().copied().collect();
+        let mut c_tab = Vec::with_capacity(alphabet.len());
+        let mut sum = 1;
+        for count in counts.values() {
+            c_tab.push(sum);
+            sum += count;
+        }
+        drop(counts);
+
+        let code = |c: usize| alphabet.binary_search(&c).unwrap();
+        let width = usize::BITS - alphabet.len().saturating_sub(1).leading_zeros();
+        let wm = WaveletCodes::new(chars().map(code).collect(), width);
+        drop(b);
+
+        // Row r+1 is the suffix at sa[r]; the sentinel row is never sampled.
+        let sa = sa.as_slice();
+        let sampled = RankBits::new(rows, |r| {
+            r > 0 && sa[r - 1].index().is_multiple_of(sample_rate)
+        });
+        let samples = (1..rows)
+            .filter(|&r| sampled.get(r))
+            .map(|r| sa[r - 1])
+            .collect();
+
+        FmIndex {
+            alphabet,
+            c_tab,
+            wm,
+            primary,
+            len: rows - 1,
+            sample_rate,
+            sampled,
+            samples,
+            _index: PhantomData,
+        }
+    }
+
+    /// The length of the text.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    pub fn sample_rate(&self) -> usize {
+        self.sample_rate
+    }
+
+    // Where row r is in the wavelet matrix, which skips the primary row
+    #[inline]
+    fn wm_pos(&self, r: usize) -> usize {
+        r - (r > self.primary) as usize
+    }
+
+    // Number of characters with code a in rows [0, r)
+    #[inline]
+    fn occ(&self, a: usize, r: usize) -> usize {
+        self.wm.rank(a, self.wm_pos(r))
+    }
+
+    // The row of the suffix one character before the suffix in row r,
+    // which must not be the primary row.
+    #[inline]
+    fn lf(&self, r: usize) -> usize {
+        let (a, occ) = self.wm.access_rank(self.wm_pos(r));
+        self.c_tab[a] + occ
+    }
+
+    /// The suffix array positions of the suffixes that start with pattern.
+    /// We extend the match one character at a time from the back of the
+    /// pattern, narrowing the range of rows with the LF mapping. If the
+    /// pattern doesn't occur, the range is empty.
+    pub fn backward_search(&self, pattern: &IdxSlice<_Text>) -> Range<SaIdx> {
+        if pattern.is_empty() {
+            return SaIdx::from_index(0)..SaIdx::from_index(self.len());
+        }
+        let (mut sp, mut ep) = (0, self.len() + 1);
+        for c in pattern.as_slice().iter().rev() {
+            let Ok(a) = self.alphabet.binary_search(&c.index()) else {
+                (sp, ep) = (1, 1);
+                break;
+            };
+            (sp, ep) = (
+                self.c_tab[a] + self.occ(a, sp),
+                self.c_tab[a] + self.occ(a, ep),
+            );
+            if sp >= ep {
+                (sp, ep) = (1, 1);
+                break;
+            }
+        }
+        SaIdx::from_index(sp - 1)..SaIdx::from_index(ep - 1)
+    }
+
+    /// The number of occurrences of pattern in the text.
+    pub fn count(&self, pattern: &IdxSlice<_Text>) -> usize {
+        let r = self.backward_search(pattern);
+        r.end.index() - r.start.index()
+    }
+
+    /// The text positions of the suffixes in the suffix array range r, in
+    /// suffix array order. We walk from each row with the LF mapping until
+    /// we hit a sampled row and add the number of steps to its sample.
+    pub fn locate(&self, r: Range<SaIdx>) -> impl Iterator<Item = TextIdx> + '_ {
+        let (i, j) = (r.start.index(), r.end.index());
+        assert!(i <= j && j <= self.len(), "range out of bounds");
+        (i + 1..j + 1).map(move |mut row| {
+            let mut steps = 0;
+            while !self.sampled.get(row) {
+                row = self.lf(row);
+                steps += 1;
+            }
+            let pos = self.samples[self.sampled.rank(row)].index() + steps;
+            TextIdx::from_index(pos)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::suffix_array::suffix_array;
+
+    #[rustfmt::skip]
+    mod types {
+        use crate::*;
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Text();
+        impl SeqTrait for Text { type Type = u8; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct TextIdx {}
+        impl TypeTrait for TextIdx { type Type = u32; }
+        impl CanIndex<Text> for TextIdx {}
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct SA();
+        impl SeqTrait for SA { type Type = Val<TextIdx>; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct SaIdx {}
+        impl TypeTrait for SaIdx { type Type = u32; }
+        impl CanIndex<SA> for SaIdx {}
+    }
+    use types::*;
+
+    type Fm = FmIndex<Text, Val<SaIdx>, Val<TextIdx>>;
+
+    fn occurrences(x: &[u8], p: &[u8]) -> Vec<usize> {
+        (0..=x.len().saturating_sub(p.len()))
+            .filter(|&i| x[i..].starts_with(p))
+            .collect()
+    }
+
+    fn check_fm(x: &[u8], patterns: &[&[u8]]) {
+        let text: IdxVec<Text> = x.to_vec().into();
+        let sa: IdxVec<SA> = suffix_array(&text);
+        for rate in [1, 3, 32] {
+            let fm = Fm::new(&text, &sa, rate);
+            assert_eq!(x.len(), fm.len());
+            for &p in patterns {
+                let r = fm.backward_search(p.into());
+                let (i, j) = (r.start.index(), r.end.index());
+                // The range holds exactly the suffixes starting with p
+                for k in 0..x.len() {
+                    let starts = x[sa[Val::<SaIdx>::from(k)].index()..].starts_with(p);
+                    assert_eq!(starts, i <= k && k < j);
+                }
+                assert_eq!(j - i, fm.count(p.into()));
+
+                let located: Vec<Val<TextIdx>> = fm.locate(r.clone()).collect();
+                let expected: Vec<_> = (i..j).map(|k| sa[Val::<SaIdx>::from(k)]).collect();
+                assert_eq!(expected, located);
+                let mut located: Vec<usize> = located.iter().map(|i| i.index()).collect();
+                located.sort();
+                if !p.is_empty() {
+                    assert_eq!(occurrences(x, p), located);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_fm_index() {
+        let pats: [&[u8]; 8] = [
+            b"",
+            b"i",
+            b"ss",
+            b"issi",
+            b"ssi",
+            b"pi",
+            b"x",
+            b"mississippii",
+        ];
+        check_fm(b"mississippi", &pats);
+        check_fm(b"", &pats);
+        check_fm(b"aaaaaaaa", &[b"a", b"aaa", b"aaaaaaaaa", b"b"]);
+
+        let x: Vec<u8> = (0..3000u32)
+            .map(|i| b"acgt"[(i * i % 7 + i / 3) as usize % 4])
+            .collect();
+        check_fm(
+            &x,
+            &[b"a", b"acg", b"gattaca", b"tt", b"cgta", &x[100..130]],
+        );
+    }
+
+    #[test]
+    fn test_alphabets() {
+        // A few characters far apart, and a number of them that isn't a
+        // power of two
+        let x: Vec<u8> = (0..1500u32)
+            .map(|i| [3, 17, 200, 201, 255][(i * i % 13 + i / 5) as usize % 5])
+            .collect();
+        check_fm(
+            &x,
+            &[&[3], &[200, 201], &[0], &[202], &[255, 3], &x[700..720]],
+        );
+        check_fm(&[7; 700], &[&[7], &[7; 64], &[8], &[7; 701]]);
+
+        // Only the characters that occur take up space
+        let text: IdxVec<Text> = x.into();
+        let sa: IdxVec<SA> = suffix_array(&text);
+        let fm = Fm::new(&text, &sa, 8);
+        assert_eq!(vec![3, 17, 200, 201, 255], fm.alphabet);
+        assert_eq!(3, fm.wm.width());
+    }
+
+    #[test]
+    fn test_typed_search() {
+        let text: IdxVec<Text> = b"banana".to_vec().into();
+        let sa: IdxVec<SA> = suffix_array(&text);
+        let fm = Fm::new(&text, &sa, 2);
+        let ana: &IdxSlice<Text> = b"ana".as_slice().into();
+        let r: Range<Val<SaIdx>> = fm.backward_search(ana);
+        assert_eq!(Val::<SaIdx>(1)..Val(3), r);
+        let pos: Vec<Val<TextIdx>> = fm.locate(r).collect();
+        assert_eq!(vec![Val::<TextIdx>(3), Val(1)], pos);
+        assert_eq!(b'a', text[pos[0]]);
+    }
+}
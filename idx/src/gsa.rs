@@ -0,0 +1,226 @@
+use crate::sa_search::search_sorted;
+use crate::suffix_array::sort_suffixes;
+use crate::*;
+use std::ops::Range;
+
+/// Builds the generalized suffix array of a collection of strings: the
+/// suffixes of all the strings, in sorted order, as (string, position)
+/// pairs of type (StrIdx, PosIdx). Positions are within their own string,
+/// so PosIdx indexes the strings, and there is no concatenated buffer to
+/// get offsets into.
+///
+/// We run SA-IS on the concatenation with a separator after each string.
+/// The separators are smaller than all characters and distinct, so a
+/// comparison never goes past the end of a string: each suffix sorts as
+/// if its string ended there, and suffixes that are equal sort by their
+/// string. The separators take up room in the alphabet, so this is for
+/// small alphabets, like suffix_array, with any number of strings.
+pub fn generalized_suffix_array<_Text, _Gsa, StrIdx, PosIdx>(
+    strings: &[&IdxSlice<_Text>],
+) -> IdxVec<_Gsa>
+where
+    _Text: SeqTrait,
+    _Text::Type: IndexType + Copy,
+    _Gsa: SeqTrait<Type = (StrIdx, PosIdx)>,
+    StrIdx: IndexType,
+    PosIdx: IndexType + CanIndex<_Text>,
+{
+    // 0 is the sentinel, 1..=m the separators, and characters come after.
+    let m = strings.len();
+    let n: usize = strings.iter().map(|x| x.len()).sum();
+    let mut starts = Vec::with_capacity(m);
+    let mut start = 0;
+    for x in strings {
+        starts.push(start);
+        start += x.len() + 1;
+    }
+    let chars = strings.iter().enumerate().flat_map(|(j, x)| {
+        let x = x.as_slice().iter().map(move |c| c.index() + m + 1);
+        x.chain([j + 1])
+    });
+    let k = chars.clone().max().map_or(1, |c| c + 1);
+
+    // The sentinel and the separators come first; drop them.
+    sort_suffixes(chars, n + m, k, m + 1, |i| {
+        let j = starts.partition_point(|&start| start <= i) - 1;
+        (StrIdx::from_index(j), PosIdx::from_index(i - starts[j]))
+    })
+    .into()
+}
+
+// The suffix in row r of the generalized suffix array
+fn suffix<'a, _Text, _Gsa, StrIdx, PosIdx>(
+    strings: &'a [&'a IdxSlice<_Text>],
+    gsa: &IdxSlice<_Gsa>,
+    r: usize,
+) -> &'a [_Text::Type]
+where
+    _Text: SeqTrait,
+    _Gsa: SeqTrait<Type = (StrIdx, PosIdx)>,
+    StrIdx: IndexType + Copy,
+    PosIdx: IndexType + Copy,
+{
+    let (j, i) = gsa.as_slice()[r];
+    &strings[j.index()].as_slice()[i.index()..]
+}
+
+/// The positions in the generalized suffix array of the suffixes that
+/// start with pattern, with the same binary search as sa_search. A match
+/// never spans two strings.
+pub fn gsa_search<_Text, _Gsa, StrIdx, PosIdx, SaIdx>(
+    strings: &[&IdxSlice<_Text>],
+    gsa: &IdxSlice<_Gsa>,
+    pattern: &IdxSlice<_Text>,
+) -> Range<SaIdx>
+where
+    _Text: SeqTrait,
+    _Text::Type: Ord,
+    _Gsa: SeqTrait<Type = (StrIdx, PosIdx)>,
+    StrIdx: IndexType + Copy,
+    PosIdx: IndexType + CanIndex<_Text> + Copy,
+    SaIdx: IndexType + CanIndex<_Gsa>,
+{
+    let (i, j) = search_sorted(gsa.len(), |r| suffix(strings, gsa, r), pattern.as_slice());
+    SaIdx::from_index(i)..SaIdx::from_index(j)
+}
+
+/// The number of occurrences of pattern in all the strings.
+pub fn gsa_count<_Text, _Gsa, StrIdx, PosIdx>(
+    strings: &[&IdxSlice<_Text>],
+    gsa: &IdxSlice<_Gsa>,
+    pattern: &IdxSlice<_Text>,
+) -> usize
+where
+    _Text: SeqTrait,
+    _Text::Type: Ord,
+    _Gsa: SeqTrait<Type = (StrIdx, PosIdx)>,
+    StrIdx: IndexType + Copy,
+    PosIdx: IndexType + CanIndex<_Text> + Copy,
+{
+    let (i, j) = search_sorted(gsa.len(), |r| suffix(strings, gsa, r), pattern.as_slice());
+    j - i
+}
+
+/// The (string, position) pairs where pattern occurs, in suffix array
+/// order.
+pub fn gsa_locate<'a, _Text, _Gsa, StrIdx, PosIdx>(
+    strings: &[&IdxSlice<_Text>],
+    gsa: &'a IdxSlice<_Gsa>,
+    pattern: &IdxSlice<_Text>,
+) -> impl Iterator<Item = (StrIdx, PosIdx)> + 'a
+where
+    _Text: SeqTrait,
+    _Text::Type: Ord,
+    _Gsa: SeqTrait<Type = (StrIdx, PosIdx)>,
+    StrIdx: IndexType + Copy + 'a,
+    PosIdx: IndexType + CanIndex<_Text> + Copy + 'a,
+{
+    let (i, j) = search_sorted(gsa.len(), |r| suffix(strings, gsa, r), pattern.as_slice());
+    gsa.as_slice()[i..j].iter().copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rustfmt::skip]
+    mod types {
+        use crate::*;
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Read();
+        impl SeqTrait for Read { type Type = u8; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct ReadIdx {}
+        impl TypeTrait for ReadIdx { type Type = u32; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Pos {}
+        impl TypeTrait for Pos { type Type = u16; }
+        impl CanIndex<Read> for Pos {}
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Gsa();
+        impl SeqTrait for Gsa { type Type = (Val<ReadIdx>, Val<Pos>); }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct SaIdx {}
+        impl TypeTrait for SaIdx { type Type = u32; }
+        impl CanIndex<Gsa> for SaIdx {}
+    }
+    use types::*;
+
+    fn raw(gsa: &IdxSlice<Gsa>) -> Vec<(usize, usize)> {
+        gsa.as_slice()
+            .iter()
+            .map(|(j, i)| (j.index(), i.index()))
+            .collect()
+    }
+
+    fn check_gsa(xs: &[&[u8]], patterns: &[&[u8]]) {
+        let reads: Vec<IdxVec<Read>> = xs.iter().map(|x| x.to_vec().into()).collect();
+        let strings: Vec<&IdxSlice<Read>> = reads.iter().map(|r| &**r).collect();
+        let gsa: IdxVec<Gsa> = generalized_suffix_array(&strings);
+
+        // Suffixes sort as if each string ended at its end, with ties
+        // broken by string
+        let mut expected: Vec<(usize, usize)> = xs
+            .iter()
+            .enumerate()
+            .flat_map(|(j, x)| (0..x.len()).map(move |i| (j, i)))
+            .collect();
+        expected.sort_by(|&(j1, i1), &(j2, i2)| xs[j1][i1..].cmp(&xs[j2][i2..]).then(j1.cmp(&j2)));
+        assert_eq!(expected, raw(&gsa));
+
+        for &p in patterns {
+            let mut found: Vec<(usize, usize)> = gsa_locate(&strings, &gsa, p.into())
+                .map(|(j, i)| (j.index(), i.index()))
+                .collect();
+            found.sort();
+            let mut occ: Vec<(usize, usize)> = expected
+                .iter()
+                .copied()
+                .filter(|&(j, i)| xs[j][i..].starts_with(p))
+                .collect();
+            occ.sort();
+            assert_eq!(occ, found);
+            assert_eq!(occ.len(), gsa_count(&strings, &gsa, p.into()));
+            let r: Range<Val<SaIdx>> = gsa_search(&strings, &gsa, p.into());
+            assert_eq!(occ.len(), r.end.index() - r.start.index());
+        }
+    }
+
+    #[test]
+    fn test_gsa() {
+        let pats: [&[u8]; 7] = [b"", b"a", b"an", b"ana", b"nab", b"xyz", b"anana"];
+        check_gsa(&[b"banana", b"ananas", b"nab"], &pats);
+        check_gsa(&[b"aaa", b"aa", b"a", b"aaa"], &[b"a", b"aa", b"aaaa"]);
+        check_gsa(&[b"", b"ab", b""], &pats);
+        check_gsa(&[], &pats);
+        let xs: Vec<Vec<u8>> = (0..20u32)
+            .map(|j| {
+                (0..30 + j)
+                    .map(|i| b"acgt"[((i * i + j) % 7 % 4) as usize])
+                    .collect()
+            })
+            .collect();
+        let xs: Vec<&[u8]> = xs.iter().map(|x| x.as_slice()).collect();
+        check_gsa(&xs, &[b"a", b"acg", b"tt", b"ca", b"gattaca"]);
+    }
+
+    #[test]
+    fn test_typed_pairs() {
+        let reads: Vec<IdxVec<Read>> = vec![b"banana".to_vec().into(), b"ananas".to_vec().into()];
+        let strings: Vec<&IdxSlice<Read>> = reads.iter().map(|r| &**r).collect();
+        let gsa: IdxVec<Gsa> = generalized_suffix_array(&strings);
+        let nas: &IdxSlice<Read> = b"nas".as_slice().into();
+        let hits: Vec<(Val<ReadIdx>, Val<Pos>)> = gsa_locate(&strings, &gsa, nas).collect();
+        assert_eq!(vec![(Val::<ReadIdx>(1), Val::<Pos>(3))], hits);
+        let (j, i) = hits[0];
+        assert_eq!(b'n', reads[j.index()][i]);
+        // "aan" would only match across the end of banana into ananas
+        let aan: &IdxSlice<Read> = b"aan".as_slice().into();
+        assert_eq!(0, gsa_count(&strings, &gsa, aan));
+    }
+}
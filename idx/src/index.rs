@@ -22,12 +22,15 @@ mod index_type {
     use crate::{NumType, TypeTrait, Val};
     pub trait IndexType: private::Seal {
         fn index(self) -> usize;
+        fn from_index(i: usize) -> Self;
     }
     impl<T: NumType + private::Seal> IndexType for T {
         #[inline] fn index(self) -> usize { self.cast() }
+        #[inline] fn from_index(i: usize) -> Self { i.cast() }
     }
     impl<_Tag: TypeTrait> IndexType for Val<_Tag> {
         #[inline] fn index(self) -> usize { self.cast() }
+        #[inline] fn from_index(i: usize) -> Self { Val(i.cast()) }
     }
 }
 pub use index_type::IndexType;
@@ -0,0 +1,265 @@
+use crate::rmq::{BlockRMQImpl, RangeMinQuery, Smallest};
+use crate::*;
+
+/// The sequence of node depths along an Euler tour. This is what we
+/// build the RMQ over; the shallowest node between two nodes' positions
+/// in the tour is their lowest common ancestor.
+#[derive(Clone, Copy, Debug)]
+pub struct EulerDepths;
+impl SeqTrait for EulerDepths {
+    type Type = usize;
+}
+impl CanIndex<EulerDepths> for usize {}
+
+/// Lowest common ancestor queries on a rooted tree. The tree is given
+/// as a parent array, where the nodes are identified by a typed index
+/// Node, the parent of the root is None, and the parent of every other
+/// node is Some(parent).
+///
+/// We build the Euler tour of the tree, the sequence of nodes we visit
+/// in a depth-first traversal, listing a node when we enter it and every
+/// time we return to it from a child, together with the depth of each
+/// node in the tour. The lowest common ancestor of u and v is the
+/// shallowest node in the tour between the first occurrence of u and
+/// the first occurrence of v, so with an O(1) RMQ, R, over the depths,
+/// queries are O(1) as well.
+pub struct Lca<_Tag: SeqTrait, Node, R = BlockRMQImpl<EulerDepths>> {
+    root: Node,
+    // The nodes in the order we visit them
+    tour: Vec<Node>,
+    // First position of each node in the tour
+    first: Vec<usize>,
+    // RMQ over the depths along the tour
+    rmq: R,
+    _tag: std::marker::PhantomData<_Tag>,
+}
+
+impl<_Tag, Node> Lca<_Tag, Node>
+where
+    _Tag: SeqTrait<Type = Option<Node>>,
+    Node: IndexType + CanIndex<_Tag> + Copy,
+{
+    /// Preprocess the tree with the parent array parents.
+    pub fn new(parents: &IdxSlice<_Tag>) -> Lca<_Tag, Node> {
+        Self::build(parents)
+    }
+}
+
+impl<_Tag, Node, R> Lca<_Tag, Node, R>
+where
+    _Tag: SeqTrait<Type = Option<Node>>,
+    Node: IndexType + CanIndex<_Tag> + Copy,
+    R: RangeMinQuery<EulerDepths, Order = Smallest>,
+{
+    /// Preprocess the tree with the parent array parents, using R
+    /// for the range minimum queries.
+    pub fn build(parents: &IdxSlice<_Tag>) -> Lca<_Tag, Node, R> {
+        let n = parents.len();
+        assert!(n > 0, "the tree must have at least one node");
+
+        // Children lists, laid out consecutively in one vector as
+        // children[start[v]..start[v+1]].
+        let mut start = vec![0; n + 1];
+        let mut roots = vec![];
+        for (v, p) in parents.as_slice().iter().enumerate() {
+            match p {
+                Some(p) => start[p.index() + 1] += 1,
+                None => roots.push(v),
+            }
+        }
+        assert!(roots.len() == 1, "the tree must have exactly one root");
+        let root = roots[0];
+        for v in 0..n {
+            start[v + 1] += start[v];
+        }
+        let mut next = start.clone();
+        let mut children = vec![0; n - 1];
+        for (v, p) in parents.as_slice().iter().enumerate() {
+            if let Some(p) = p {
+                children[next[p.index()]] = v;
+                next[p.index()] += 1;
+            }
+        }
+
+        // Depth-first traversal, with an explicit stack so deep trees
+        // don't overflow the call stack. The stack holds the path from
+        // the root, with the next child to visit for each node on it.
+        let mut tour = Vec::with_capacity(2 * n - 1);
+        let mut depths = Vec::with_capacity(2 * n - 1);
+        let mut first = vec![usize::MAX; n];
+        first[root] = 0;
+        tour.push(Node::from_index(root));
+        depths.push(0);
+        let mut stack = vec![(root, start[root])];
+        while let Some((v, child)) = stack.last_mut() {
+            if *child < start[*v + 1] {
+                let w = children[*child];
+                *child += 1;
+                first[w] = tour.len();
+                tour.push(Node::from_index(w));
+                depths.push(stack.len());
+                stack.push((w, start[w]));
+            } else {
+                stack.pop();
+                if let Some(&(p, _)) = stack.last() {
+                    tour.push(Node::from_index(p));
+                    depths.push(stack.len() - 1);
+                }
+            }
+        }
+        // A node we never reached is on a cycle, not below the root.
+        assert!(
+            first.iter().all(|&i| i != usize::MAX),
+            "the parent array has a cycle"
+        );
+
+        Lca {
+            root: Node::from_index(root),
+            tour,
+            first,
+            rmq: R::build(depths.into(), Smallest),
+            _tag: std::marker::PhantomData,
+        }
+    }
+
+    /// The number of nodes in the tree.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.first.len()
+    }
+
+    /// Never true; a tree has at least its root.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.first.is_empty()
+    }
+
+    #[inline]
+    pub fn root(&self) -> Node {
+        self.root
+    }
+
+    /// The depth of node u, where the root has depth zero.
+    #[inline]
+    pub fn depth(&self, u: Node) -> usize {
+        self.rmq.values()[self.first[u.index()]]
+    }
+
+    /// The Euler tour of the tree.
+    #[inline]
+    pub fn tour(&self) -> &[Node] {
+        &self.tour
+    }
+
+    /// The lowest common ancestor of u and v.
+    pub fn lca(&self, u: Node, v: Node) -> Node {
+        let (i, j) = (self.first[u.index()], self.first[v.index()]);
+        let (i, j) = (usize::min(i, j), usize::max(i, j));
+        self.tour[self.rmq.rmq(i..j + 1).idx()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rmq::{NaiveRMQImpl, PowerRMQImpl};
+
+    #[rustfmt::skip]
+    mod types {
+        use crate::*;
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Node {}
+        impl TypeTrait for Node { type Type = u32; }
+        impl CanIndex<Parents> for Node {}
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Parents();
+        impl SeqTrait for Parents { type Type = Option<Val<Node>>; }
+    }
+    use types::*;
+
+    fn node(i: usize) -> Val<Node> {
+        i.into()
+    }
+
+    fn parents(p: &[Option<usize>]) -> IdxVec<Parents> {
+        p.iter().map(|p| p.map(node)).collect::<Vec<_>>().into()
+    }
+
+    // Walk up from the deeper node until the two paths meet.
+    fn naive_lca(parents: &IdxSlice<Parents>, u: Val<Node>, v: Val<Node>) -> Val<Node> {
+        let path = |mut u: Val<Node>| {
+            let mut path = vec![u];
+            while let Some(p) = parents[u] {
+                path.push(p);
+                u = p;
+            }
+            path
+        };
+        let (pu, pv) = (path(u), path(v));
+        *pu.iter().find(|w| pv.contains(w)).unwrap()
+    }
+
+    fn check_lca<R>(p: &IdxVec<Parents>)
+    where
+        R: RangeMinQuery<EulerDepths, Order = Smallest>,
+    {
+        let lca: Lca<Parents, Val<Node>, R> = Lca::build(p);
+        assert_eq!(2 * p.len() - 1, lca.tour().len());
+        for u in 0..p.len() {
+            for v in 0..p.len() {
+                assert_eq!(naive_lca(p, node(u), node(v)), lca.lca(node(u), node(v)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_lca() {
+        //          3
+        //        / | \
+        //       0  5  7
+        //      / \     \
+        //     1   6     4
+        //     |
+        //     2
+        let p = parents(&[
+            Some(3),
+            Some(0),
+            Some(1),
+            None,
+            Some(7),
+            Some(3),
+            Some(0),
+            Some(3),
+        ]);
+        let lca: Lca<Parents, Val<Node>> = Lca::new(&p);
+        assert_eq!(8, lca.len());
+        assert_eq!(node(3), lca.root());
+        assert_eq!(3, lca.depth(node(2)));
+        assert_eq!(node(0), lca.lca(node(2), node(6)));
+        assert_eq!(node(3), lca.lca(node(2), node(4)));
+        assert_eq!(node(1), lca.lca(node(1), node(2)));
+        assert_eq!(node(5), lca.lca(node(5), node(5)));
+
+        check_lca::<BlockRMQImpl<EulerDepths>>(&p);
+        check_lca::<PowerRMQImpl<EulerDepths>>(&p);
+        check_lca::<NaiveRMQImpl<EulerDepths>>(&p);
+
+        // A single node, a path, and a star
+        check_lca::<BlockRMQImpl<EulerDepths>>(&parents(&[None]));
+        let path: Vec<_> = (0usize..50).map(|i| i.checked_sub(1)).collect();
+        check_lca::<BlockRMQImpl<EulerDepths>>(&parents(&path));
+        let star: Vec<_> = (0..50)
+            .map(|i| if i == 0 { None } else { Some(0) })
+            .collect();
+        check_lca::<BlockRMQImpl<EulerDepths>>(&parents(&star));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_lca_cycle() {
+        let p = parents(&[None, Some(2), Some(1)]);
+        let _: Lca<Parents, Val<Node>> = Lca::new(&p);
+    }
+}
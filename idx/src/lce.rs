@@ -0,0 +1,148 @@
+use crate::lcp::lcp_kasai;
+use crate::permutation::Permutation;
+use crate::rmq::{BlockRMQImpl, RangeMinQuery};
+use crate::suffix_array::suffix_array;
+use crate::*;
+use std::marker::PhantomData;
+
+// Ranks of suffixes, which index the suffix array and the LCP array
+#[derive(Clone, Copy, Debug)]
+struct Rank {}
+impl TypeTrait for Rank {
+    type Type = usize;
+}
+
+// The suffix array we build on the way, holding text positions
+struct Positions<TextIdx>(PhantomData<TextIdx>);
+impl<TextIdx> SeqTrait for Positions<TextIdx> {
+    type Type = TextIdx;
+}
+impl<TextIdx> CanIndex<Positions<TextIdx>> for Rank {}
+
+// The LCP array we build the RMQ over
+struct LcpValues;
+impl SeqTrait for LcpValues {
+    type Type = usize;
+}
+impl CanIndex<LcpValues> for Rank {}
+
+/// Longest common extension queries: the length of the longest common
+/// prefix of the suffixes at two text positions. We build the suffix
+/// array, its inverse, and the LCP array, and the answer is the smallest
+/// LCP value between the ranks of the two suffixes, so with the O(1)
+/// BlockRMQImpl over the LCP array, queries are O(1) as well.
+///
+/// The positions are of type TextIdx and the lengths we return of type
+/// Len, so a length can't be passed off as a position.
+pub struct Lce<_Text, TextIdx, Len> {
+    // rank[i] is the position of suffix i in the suffix array
+    rank: Permutation<TextIdx, Val<Rank>>,
+    rmq: BlockRMQImpl<LcpValues>,
+    _index: PhantomData<fn(_Text) -> Len>,
+}
+
+impl<_Text, TextIdx, Len> Lce<_Text, TextIdx, Len>
+where
+    _Text: SeqTrait,
+    _Text::Type: IndexType + Copy + PartialEq,
+    TextIdx: IndexType + CanIndex<_Text> + Copy,
+    Len: IndexType,
+{
+    /// Preprocess text for LCE queries.
+    pub fn new(text: &IdxSlice<_Text>) -> Lce<_Text, TextIdx, Len> {
+        let sa: IdxVec<Positions<TextIdx>> = suffix_array(text);
+        let lcp: IdxVec<LcpValues> = lcp_kasai(text, &sa);
+        let sa: Permutation<Val<Rank>, TextIdx> =
+            Permutation::new(sa).expect("a suffix array is a permutation");
+        Lce {
+            rank: sa.inverse(),
+            rmq: BlockRMQImpl::new(lcp),
+            _index: PhantomData,
+        }
+    }
+
+    /// The length of the text.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.rank.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.rank.is_empty()
+    }
+
+    /// The length of the longest common prefix of the suffixes at i and j.
+    pub fn lce(&self, i: TextIdx, j: TextIdx) -> Len {
+        let n = self.len();
+        assert!(i.index() < n && j.index() < n, "position out of bounds");
+        if i.index() == j.index() {
+            return Len::from_index(n - i.index());
+        }
+        let (ri, rj) = (self.rank[i].0, self.rank[j].0);
+        let (ri, rj) = (usize::min(ri, rj), usize::max(ri, rj));
+        // lcp[r] is for the suffixes at ranks r-1 and r
+        let r = Val::<Rank>(ri + 1)..Val(rj + 1);
+        Len::from_index(self.rmq.rmq(r).val())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rustfmt::skip]
+    mod types {
+        use crate::*;
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Text();
+        impl SeqTrait for Text { type Type = u8; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct TextIdx {}
+        impl TypeTrait for TextIdx { type Type = u32; }
+        impl CanIndex<Text> for TextIdx {}
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Offset {}
+        impl TypeTrait for Offset { type Type = u32; }
+    }
+    use types::*;
+
+    fn pos(i: usize) -> Val<TextIdx> {
+        i.into()
+    }
+
+    fn check_lce(x: &[u8]) {
+        let text: IdxVec<Text> = x.to_vec().into();
+        let lce = Lce::<Text, Val<TextIdx>, Val<Offset>>::new(&text);
+        assert_eq!(x.len(), lce.len());
+        for i in 0..x.len() {
+            for j in 0..x.len() {
+                let expected = x[i..]
+                    .iter()
+                    .zip(&x[j..])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                assert_eq!(Val::<Offset>::from(expected), lce.lce(pos(i), pos(j)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_lce() {
+        for x in [&b"mississippi"[..], b"abracadabra", b"aaaaaaa", b"a", b""] {
+            check_lce(x);
+        }
+        let x: Vec<u8> = (0..300u32)
+            .map(|i| b"acgt"[(i * i % 7 + i / 3) as usize % 4])
+            .collect();
+        check_lce(&x);
+
+        let text: IdxVec<Text> = b"abcabcab".to_vec().into();
+        let lce: Lce<Text, Val<TextIdx>, Val<Offset>> = Lce::new(&text);
+        assert_eq!(Val::<Offset>(5), lce.lce(pos(0), pos(3)));
+        assert_eq!(Val::<Offset>(0), lce.lce(pos(0), pos(1)));
+    }
+}
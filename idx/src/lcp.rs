@@ -0,0 +1,215 @@
+use crate::*;
+
+// Length of the longest common prefix of x[i..] and x[j..], starting
+// the comparison at offset h, which must be a common prefix already.
+#[inline]
+fn extend<T: PartialEq>(x: &[T], i: usize, j: usize, mut h: usize) -> usize {
+    while i + h < x.len() && j + h < x.len() && x[i + h] == x[j + h] {
+        h += 1;
+    }
+    h
+}
+
+/// Builds the LCP array for text and its suffix array sa with Kasai's
+/// algorithm, in linear time. lcp[r] is the length of the longest common
+/// prefix of the suffixes at sa[r-1] and sa[r], and lcp[0] is zero.
+///
+/// The LCP array has the same length as the suffix array and is meant to
+/// be indexed by the same index type; the lengths are of type Len, which
+/// would normally be the offset type that goes with the text positions,
+/// and not a text position itself.
+pub fn lcp_kasai<_Text, _Sa, _Lcp, Idx, Len>(
+    text: &IdxSlice<_Text>,
+    sa: &IdxSlice<_Sa>,
+) -> IdxVec<_Lcp>
+where
+    _Text: SeqTrait,
+    _Text::Type: PartialEq,
+    _Sa: SeqTrait<Type = Idx>,
+    _Lcp: SeqTrait<Type = Len>,
+    Idx: IndexType + CanIndex<_Text> + Copy,
+    Len: IndexType + Copy,
+{
+    let (x, sa) = (text.as_slice(), sa.as_slice());
+    let n = x.len();
+    assert_eq!(n, sa.len(), "the suffix array must have the text's length");
+
+    let mut rank = vec![0; n];
+    for (r, &i) in sa.iter().enumerate() {
+        rank[i.index()] = r;
+    }
+    // Going through the suffixes in text order, the lcp with the previous
+    // suffix in the suffix array drops by at most one from i to i+1.
+    let mut lcp = vec![Len::from_index(0); n];
+    let mut h = 0;
+    for i in 0..n {
+        if rank[i] == 0 {
+            h = 0;
+            continue;
+        }
+        let j = sa[rank[i] - 1].index();
+        h = extend(x, i, j, h);
+        lcp[rank[i]] = Len::from_index(h);
+        h = h.saturating_sub(1);
+    }
+    lcp.into()
+}
+
+/// Builds the LCP array with the Φ algorithm, through the permuted LCP
+/// array, PLCP, where plcp[i] is the lcp of suffix i and the suffix before
+/// it in the suffix array, Φ(i). It is linear time, like Kasai, but it
+/// goes through the text in order instead of jumping around in the suffix
+/// array, which is friendlier to the cache.
+pub fn lcp_phi<_Text, _Sa, _Lcp, Idx, Len>(
+    text: &IdxSlice<_Text>,
+    sa: &IdxSlice<_Sa>,
+) -> IdxVec<_Lcp>
+where
+    _Text: SeqTrait,
+    _Text::Type: PartialEq,
+    _Sa: SeqTrait<Type = Idx>,
+    _Lcp: SeqTrait<Type = Len>,
+    Idx: IndexType + CanIndex<_Text> + Copy,
+    Len: IndexType + Copy,
+{
+    lcp_sparse_phi(text, sa, 1)
+}
+
+/// The sparse Φ algorithm. We only compute the PLCP values for every q'th
+/// text position, which takes n/q words instead of n, and then get the
+/// rest from plcp[i+d] >= plcp[i] - d, extending each value by comparing
+/// characters. That is O(nq) time in the worst case but much less in
+/// practice.
+pub fn lcp_sparse_phi<_Text, _Sa, _Lcp, Idx, Len>(
+    text: &IdxSlice<_Text>,
+    sa: &IdxSlice<_Sa>,
+    q: usize,
+) -> IdxVec<_Lcp>
+where
+    _Text: SeqTrait,
+    _Text::Type: PartialEq,
+    _Sa: SeqTrait<Type = Idx>,
+    _Lcp: SeqTrait<Type = Len>,
+    Idx: IndexType + CanIndex<_Text> + Copy,
+    Len: IndexType + Copy,
+{
+    assert!(q > 0, "the sampling rate must be positive");
+    let (x, sa) = (text.as_slice(), sa.as_slice());
+    let n = x.len();
+    assert_eq!(n, sa.len(), "the suffix array must have the text's length");
+
+    // Φ for the sampled positions; None for the first suffix.
+    let mut phi = vec![None; n.div_ceil(q)];
+    for r in 0..n {
+        let i = sa[r].index();
+        if i % q == 0 {
+            phi[i / q] = (r > 0).then(|| sa[r - 1].index());
+        }
+    }
+    // Sampled PLCP, in text order, where plcp[i+q] >= plcp[i] - q.
+    let mut plcp = vec![0; phi.len()];
+    let mut h = 0;
+    for (s, phi) in phi.iter().enumerate() {
+        h = match phi {
+            Some(j) => extend(x, s * q, *j, h),
+            None => 0,
+        };
+        plcp[s] = h;
+        h = h.saturating_sub(q);
+    }
+
+    let mut lcp = vec![Len::from_index(0); n];
+    for r in 1..n {
+        let (i, j) = (sa[r].index(), sa[r - 1].index());
+        let h = plcp[i / q].saturating_sub(i % q);
+        lcp[r] = Len::from_index(extend(x, i, j, h));
+    }
+    lcp.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::suffix_array::suffix_array;
+
+    #[rustfmt::skip]
+    mod types {
+        use crate::*;
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Text();
+        impl SeqTrait for Text { type Type = u8; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct TextIdx {}
+        impl TypeTrait for TextIdx { type Type = u32; }
+        impl CanIndex<Text> for TextIdx {}
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Offset {}
+        impl TypeTrait for Offset { type Type = u32; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct SA();
+        impl SeqTrait for SA { type Type = Val<TextIdx>; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Lcp();
+        impl SeqTrait for Lcp { type Type = Val<Offset>; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct SaIdx {}
+        impl TypeTrait for SaIdx { type Type = u32; }
+        impl CanIndex<SA> for SaIdx {}
+        impl CanIndex<Lcp> for SaIdx {}
+    }
+    use types::*;
+
+    fn naive_lcp(x: &[u8], sa: &IdxSlice<SA>) -> Vec<usize> {
+        let sa: Vec<usize> = sa.as_slice().iter().map(|i| i.index()).collect();
+        (0..sa.len())
+            .map(|r| match r {
+                0 => 0,
+                _ => extend(x, sa[r - 1], sa[r], 0),
+            })
+            .collect()
+    }
+
+    fn check_lcp(x: &[u8]) {
+        let text: IdxVec<Text> = x.to_vec().into();
+        let sa: IdxVec<SA> = suffix_array(&text);
+        let expected = naive_lcp(x, &sa);
+        let as_usize = |lcp: IdxVec<Lcp>| lcp.0.iter().map(|l| l.index()).collect::<Vec<_>>();
+
+        assert_eq!(expected, as_usize(lcp_kasai(&text, &sa)));
+        assert_eq!(expected, as_usize(lcp_phi(&text, &sa)));
+        for q in [2, 3, 8, 100] {
+            assert_eq!(expected, as_usize(lcp_sparse_phi(&text, &sa, q)));
+        }
+    }
+
+    #[test]
+    fn test_lcp() {
+        check_lcp(b"mississippi");
+        check_lcp(b"abracadabra");
+        check_lcp(b"aaaaaaaaaaaa");
+        check_lcp(b"gattacagattacagattaca");
+        check_lcp(b"a");
+        check_lcp(b"");
+        let x: Vec<u8> = (0..500u32)
+            .map(|i| b"acgt"[(i * i % 7 + i / 3) as usize % 4])
+            .collect();
+        check_lcp(&x);
+    }
+
+    #[test]
+    fn test_typed_lcp() {
+        let text: IdxVec<Text> = b"banana".to_vec().into();
+        let sa: IdxVec<SA> = suffix_array(&text);
+        let lcp: IdxVec<Lcp> = lcp_kasai(&text, &sa);
+        // a, ana, anana, banana, na, nana
+        let r: Val<SaIdx> = Val(2);
+        assert_eq!(Val::<Offset>(3), lcp[r]);
+        assert_eq!(Val::<TextIdx>(1), sa[r]);
+    }
+}
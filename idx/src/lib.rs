@@ -1,11 +1,12 @@
 #![feature(step_trait)]
+#![feature(portable_simd)]
+#![feature(min_specialization)]
 
 mod macros;
 
 // Base wrapper mechanism and type system
 mod wrapper;
-#[allow(unused_imports)]
-use wrapper::*;
+pub use wrapper::*;
 
 // Definining arithmetic operators on wrapped types
 mod ops;
@@ -14,17 +15,83 @@ use ops::*;
 
 // Code for treating wrapped objects with indexing
 mod index;
-#[allow(unused_imports)]
-use index::*;
+pub use index::*;
 
 // Code for wrapping sequences
 mod sequences;
-#[allow(unused_imports)]
-use sequences::*;
+pub use sequences::*;
 
 // Handling ranges of new types (with some rust-induced limits)
 mod range;
 #[allow(unused_imports)]
 use range::*;
 
-//mod rmq;
+// Flat two-dimensional tables indexed by typed indices
+mod table;
+pub use table::*;
+
+// Range minimum queries on typed sequences
+pub mod rmq;
+
+// Range queries for idempotent operations (min, max, gcd, and, or, ...)
+pub mod sparse_table;
+
+// Segment trees with lazy range updates
+pub mod segment_tree;
+
+// Fenwick trees for prefix sums
+pub mod fenwick;
+
+// Lowest common ancestors via Euler tours and RMQ
+pub mod lca;
+
+// Nearest smaller values and Cartesian trees
+pub mod cartesian;
+
+// Minimum and maximum over sliding windows
+pub mod sliding;
+
+// Storing RMQ structures in files we can memory-map
+pub mod store;
+
+// Range minimum queries over two-dimensional tables
+pub mod rmq2d;
+
+// Bit vectors with rank, and the levels of wavelet matrices
+mod wavelet;
+
+// Range top-k and k-th smallest queries
+pub mod topk;
+
+// Suffix array construction
+pub mod suffix_array;
+
+// Longest common prefix arrays
+pub mod lcp;
+
+// Permutations between two index types
+pub mod permutation;
+
+// Burrows-Wheeler transform
+pub mod bwt;
+
+// FM-index for counting and locating patterns
+pub mod fm_index;
+
+// Pattern search on plain suffix arrays
+pub mod sa_search;
+
+// Longest common extensions via suffix arrays and RMQ
+pub mod lce;
+
+// Enhanced suffix arrays: lcp-intervals and the child table
+pub mod esa;
+
+// Suffix trees from suffix and LCP arrays
+pub mod suffix_tree;
+
+// Generalized suffix arrays over collections of strings
+pub mod gsa;
+
+// Z-arrays, border arrays and Knuth-Morris-Pratt matching
+pub mod matching;
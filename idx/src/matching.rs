@@ -0,0 +1,237 @@
+use crate::*;
+
+/// The Z-array of x: z[i] is the length of the longest common prefix of x
+/// and x[i..], and z[0] is the length of x. The result is a sequence of
+/// lengths of type Len, meant to be indexed by the same index type as x.
+///
+/// We keep the rightmost match [l,r) we have seen, x[l..r] == x[..r-l].
+/// For i inside it, x[i..r] is a copy of x[i-l..r-l], so z[i] is at least
+/// min(z[i-l], r-i), and we only compare characters past r, which moves r
+/// to the right. Linear time.
+pub fn z_array<_Pat, _Z, Len>(x: &IdxSlice<_Pat>) -> IdxVec<_Z>
+where
+    _Pat: SeqTrait,
+    _Pat::Type: PartialEq,
+    _Z: SeqTrait<Type = Len>,
+    Len: IndexType,
+{
+    let x = x.as_slice();
+    let n = x.len();
+    let mut z = vec![0; n];
+    let (mut l, mut r) = (0, 0);
+    for i in 1..n {
+        let mut k = if i < r {
+            usize::min(z[i - l], r - i)
+        } else {
+            0
+        };
+        while i + k < n && x[k] == x[i + k] {
+            k += 1;
+        }
+        z[i] = k;
+        if i + k > r {
+            (l, r) = (i, i + k);
+        }
+    }
+    if n > 0 {
+        z[0] = n;
+    }
+    z.into_iter()
+        .map(Len::from_index)
+        .collect::<Vec<_>>()
+        .into()
+}
+
+// Border array as plain lengths
+fn borders<T: PartialEq>(x: &[T]) -> Vec<usize> {
+    let mut b = vec![0; x.len()];
+    let mut k = 0;
+    for i in 1..x.len() {
+        // Fall back through the borders of x[..i] until one extends
+        while k > 0 && x[i] != x[k] {
+            k = b[k - 1];
+        }
+        if x[i] == x[k] {
+            k += 1;
+        }
+        b[i] = k;
+    }
+    b
+}
+
+/// The border array, or failure function, of x: b[i] is the length of the
+/// longest proper prefix of x[..=i] that is also a suffix of it. The
+/// borders of x[..=i] are b[i], b[b[i]-1], and so on, which is what KMP
+/// falls back through on a mismatch. Linear time.
+pub fn border_array<_Pat, _B, Len>(x: &IdxSlice<_Pat>) -> IdxVec<_B>
+where
+    _Pat: SeqTrait,
+    _Pat::Type: PartialEq,
+    _B: SeqTrait<Type = Len>,
+    Len: IndexType,
+{
+    borders(x.as_slice())
+        .into_iter()
+        .map(Len::from_index)
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// Knuth-Morris-Pratt matcher for a pattern. We scan the text once,
+/// keeping the length of the longest prefix of the pattern that ends at
+/// the current position, and on a mismatch fall back to its longest
+/// border instead of starting over, so a search is O(n) after O(m)
+/// preprocessing.
+pub struct Kmp<'a, _Pat: SeqTrait> {
+    pattern: &'a IdxSlice<_Pat>,
+    borders: Vec<usize>,
+}
+
+impl<'a, _Pat> Kmp<'a, _Pat>
+where
+    _Pat: SeqTrait,
+    _Pat::Type: PartialEq,
+{
+    pub fn new(pattern: &'a IdxSlice<_Pat>) -> Kmp<'a, _Pat> {
+        Kmp {
+            pattern,
+            borders: borders(pattern.as_slice()),
+        }
+    }
+
+    #[inline]
+    pub fn pattern(&self) -> &'a IdxSlice<_Pat> {
+        self.pattern
+    }
+
+    /// The start positions of the occurrences of the pattern in text, in
+    /// increasing order, including overlapping ones. The empty pattern
+    /// occurs at every position, up to and including the end of the text.
+    pub fn find_iter<'b, _Text, TextIdx>(
+        &'b self,
+        text: &'b IdxSlice<_Text>,
+    ) -> impl Iterator<Item = TextIdx> + 'b
+    where
+        _Text: SeqTrait<Type = _Pat::Type>,
+        TextIdx: IndexType + CanIndex<_Text> + 'b,
+    {
+        let (p, x) = (self.pattern.as_slice(), text.as_slice());
+        let m = p.len();
+        let mut k = 0;
+        let empty = (m == 0).then_some(0..=x.len()).into_iter().flatten();
+        let found = x.iter().enumerate().filter_map(move |(i, c)| {
+            if m == 0 {
+                return None;
+            }
+            if k == m {
+                k = self.borders[m - 1];
+            }
+            while k > 0 && *c != p[k] {
+                k = self.borders[k - 1];
+            }
+            if *c == p[k] {
+                k += 1;
+            }
+            (k == m).then(|| i + 1 - m)
+        });
+        empty.chain(found).map(TextIdx::from_index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rustfmt::skip]
+    mod types {
+        use crate::*;
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Pattern();
+        impl SeqTrait for Pattern { type Type = u8; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Text();
+        impl SeqTrait for Text { type Type = u8; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Len {}
+        impl TypeTrait for Len { type Type = u32; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Lens();
+        impl SeqTrait for Lens { type Type = Val<Len>; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct PatIdx {}
+        impl TypeTrait for PatIdx { type Type = u32; }
+        impl CanIndex<Pattern> for PatIdx {}
+        impl CanIndex<Lens> for PatIdx {}
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct TextIdx {}
+        impl TypeTrait for TextIdx { type Type = u32; }
+        impl CanIndex<Text> for TextIdx {}
+    }
+    use types::*;
+
+    fn raw(v: IdxVec<Lens>) -> Vec<usize> {
+        v.0.iter().map(|l| l.index()).collect()
+    }
+
+    fn check(p: &[u8], x: &[u8]) {
+        let pat: &IdxSlice<Pattern> = p.into();
+        let lcp = |a: &[u8], b: &[u8]| a.iter().zip(b).take_while(|(c, d)| c == d).count();
+
+        let z: Vec<usize> = (0..p.len()).map(|i| lcp(p, &p[i..])).collect();
+        assert_eq!(z, raw(z_array(pat)));
+
+        let b: Vec<usize> = (0..p.len())
+            .map(|i| (0..=i).rev().find(|&k| p[..k] == p[i + 1 - k..=i]).unwrap())
+            .collect();
+        assert_eq!(b, raw(border_array(pat)));
+
+        let text: &IdxSlice<Text> = x.into();
+        let expected: Vec<usize> = (0..=x.len()).filter(|&i| x[i..].starts_with(p)).collect();
+        let kmp = Kmp::new(pat);
+        let found: Vec<Val<TextIdx>> = kmp.find_iter(text).collect();
+        assert_eq!(
+            expected,
+            found.iter().map(|i| i.index()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_matching() {
+        check(b"aabxaab", b"aabxaabxaabaab");
+        check(b"abab", b"abababab");
+        check(b"aaaa", b"aaaaaaa");
+        check(b"abc", b"ababcabcab");
+        check(b"x", b"abc");
+        check(b"abc", b"ab");
+        check(b"a", b"");
+        check(b"", b"abc");
+        check(b"", b"");
+        let x: Vec<u8> = (0..1000u32)
+            .map(|i| b"ab"[((i * i + i / 5) % 3 % 2) as usize])
+            .collect();
+        check(&x[10..20], &x);
+        check(b"abab", &x);
+    }
+
+    #[test]
+    fn test_typed_arrays() {
+        let pat: &IdxSlice<Pattern> = b"abacaba".as_slice().into();
+        let z: IdxVec<Lens> = z_array(pat);
+        let b: IdxVec<Lens> = border_array(pat);
+        let i: Val<PatIdx> = Val(4);
+        assert_eq!(b'a', pat[i]);
+        assert_eq!(Val::<Len>(3), z[i]);
+        assert_eq!(Val::<Len>(3), b[Val::<PatIdx>(6)]);
+
+        let text: &IdxSlice<Text> = b"abacabacaba".as_slice().into();
+        let hits: Vec<Val<TextIdx>> = Kmp::new(pat).find_iter(text).collect();
+        assert_eq!(vec![Val::<TextIdx>(0), Val(4)], hits);
+        assert_eq!(b"abaca", text[hits[1]..Val(9)].as_slice());
+    }
+}
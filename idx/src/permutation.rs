@@ -0,0 +1,343 @@
+use crate::*;
+use std::marker::PhantomData;
+use std::ops::Index;
+
+// Sequence tag for the images in a permutation
+struct Images<From, To>(PhantomData<fn(From) -> To>);
+impl<From, To> SeqTrait for Images<From, To> {
+    type Type = To;
+}
+
+/// Why a sequence isn't a permutation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PermutationError {
+    /// The value at position pos is outside [0,len).
+    OutOfRange {
+        pos: usize,
+        value: usize,
+        len: usize,
+    },
+    /// The value appears at both positions first and second.
+    Repeated {
+        value: usize,
+        first: usize,
+        second: usize,
+    },
+}
+
+impl std::fmt::Display for PermutationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use PermutationError::*;
+        match self {
+            OutOfRange { pos, value, len } => {
+                write!(
+                    f,
+                    "value {} at position {} is not below {}",
+                    value, pos, len
+                )
+            }
+            Repeated {
+                value,
+                first,
+                second,
+            } => write!(
+                f,
+                "value {} appears at both position {} and {}",
+                value, first, second
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PermutationError {}
+
+// Checks that v holds each of [0,len) exactly once
+fn check<T: IndexType + Copy>(v: &[T]) -> Result<(), PermutationError> {
+    let len = v.len();
+    let mut seen = vec![usize::MAX; len];
+    for (pos, &x) in v.iter().enumerate() {
+        let value = x.index();
+        if value >= len {
+            return Err(PermutationError::OutOfRange { pos, value, len });
+        }
+        if seen[value] != usize::MAX {
+            return Err(PermutationError::Repeated {
+                value,
+                first: seen[value],
+                second: pos,
+            });
+        }
+        seen[value] = pos;
+    }
+    Ok(())
+}
+
+/// A permutation of [0,n) that maps indices of type From to indices of
+/// type To. A suffix array is one from suffix array positions to text
+/// positions, and its inverse goes the other way, so with the index types
+/// in the permutation we can't look up a text position where we want a
+/// rank.
+pub struct Permutation<From, To> {
+    images: IdxVec<Images<From, To>>,
+}
+
+impl<From, To> Permutation<From, To>
+where
+    From: IndexType + Copy,
+    To: IndexType + Copy,
+{
+    /// Takes over a sequence indexed by From that holds To values, after
+    /// checking that it is a permutation.
+    pub fn new<_Tag>(seq: IdxVec<_Tag>) -> Result<Permutation<From, To>, PermutationError>
+    where
+        _Tag: SeqTrait<Type = To>,
+        From: CanIndex<_Tag>,
+    {
+        check(seq.as_slice())?;
+        Ok(Permutation {
+            images: seq.0.into(),
+        })
+    }
+
+    // For sequences we know are permutations
+    fn from_vec_unchecked(v: Vec<To>) -> Permutation<From, To> {
+        debug_assert!(check(&v).is_ok());
+        Permutation { images: v.into() }
+    }
+
+    /// The identity on [0,n).
+    pub fn identity(n: usize) -> Permutation<From, To> {
+        Self::from_vec_unchecked((0..n).map(To::from_index).collect())
+    }
+
+    /// The permutation that sorts x: it maps each rank to the position
+    /// of the value with that rank, with equal values kept in the order
+    /// they have in x, so applying it to x gives the sorted sequence.
+    pub fn sorting<_Tag>(x: &IdxSlice<_Tag>) -> Permutation<From, To>
+    where
+        _Tag: SeqTrait,
+        _Tag::Type: Ord,
+        To: CanIndex<_Tag>,
+    {
+        let x = x.as_slice();
+        let mut pos: Vec<usize> = (0..x.len()).collect();
+        pos.sort_by(|&a, &b| x[a].cmp(&x[b]));
+        Self::from_vec_unchecked(pos.into_iter().map(To::from_index).collect())
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+
+    /// The images, in the order of their pre-images.
+    #[inline]
+    pub fn as_slice(&self) -> &[To] {
+        self.images.as_slice()
+    }
+
+    /// Gives the permutation back as a sequence indexed by From.
+    pub fn into_seq<_Tag>(self) -> IdxVec<_Tag>
+    where
+        _Tag: SeqTrait<Type = To>,
+        From: CanIndex<_Tag>,
+    {
+        self.images.0.into()
+    }
+
+    /// The inverse permutation, so inverse[p[i]] == i. The inverse of
+    /// the suffix array is the rank array.
+    pub fn inverse(&self) -> Permutation<To, From> {
+        let mut inv = vec![From::from_index(0); self.len()];
+        for (i, &j) in self.as_slice().iter().enumerate() {
+            inv[j.index()] = From::from_index(i);
+        }
+        Permutation::from_vec_unchecked(inv)
+    }
+
+    /// The permutation that first maps with self and then with next,
+    /// i -> next[self[i]].
+    pub fn compose<Next>(&self, next: &Permutation<To, Next>) -> Permutation<From, Next>
+    where
+        Next: IndexType + Copy,
+    {
+        assert_eq!(
+            self.len(),
+            next.len(),
+            "permutations must have the same length"
+        );
+        let v = self.as_slice().iter().map(|&j| next[j]).collect();
+        Permutation::from_vec_unchecked(v)
+    }
+
+    /// Reorders x, which is indexed by To, so it is indexed by From, with
+    /// y[i] = x[p[i]]. With the suffix array as p, it takes values per text
+    /// position to values per suffix. The values are moved around in x's
+    /// own buffer, following the cycles of the permutation, so it only
+    /// needs a bit per position on the side.
+    pub fn apply<_In, _Out, T>(&self, x: IdxVec<_In>) -> IdxVec<_Out>
+    where
+        _In: SeqTrait<Type = T>,
+        _Out: SeqTrait<Type = T>,
+        To: CanIndex<_In>,
+        From: CanIndex<_Out>,
+    {
+        assert_eq!(
+            self.len(),
+            x.len(),
+            "the sequence must have the permutation's length"
+        );
+        let mut v = x.0;
+        let p = self.as_slice();
+        let mut done = vec![false; v.len()];
+        for i in 0..v.len() {
+            if done[i] {
+                continue;
+            }
+            // Pull each value in the cycle one step back towards i
+            let mut j = i;
+            done[j] = true;
+            while p[j].index() != i {
+                let k = p[j].index();
+                v.swap(j, k);
+                done[k] = true;
+                j = k;
+            }
+        }
+        v.into()
+    }
+}
+
+impl<From, To: std::fmt::Debug> std::fmt::Debug for Permutation<From, To> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_list().entries(self.images.as_slice()).finish()
+    }
+}
+
+impl<From, To> Index<From> for Permutation<From, To>
+where
+    From: IndexType,
+{
+    type Output = To;
+    #[inline]
+    fn index(&self, i: From) -> &To {
+        &self.images.as_slice()[i.index()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::suffix_array::suffix_array;
+
+    #[rustfmt::skip]
+    mod types {
+        use crate::*;
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Text();
+        impl SeqTrait for Text { type Type = u8; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct TextIdx {}
+        impl TypeTrait for TextIdx { type Type = u32; }
+        impl CanIndex<Text> for TextIdx {}
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct SA();
+        impl SeqTrait for SA { type Type = Val<TextIdx>; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct SaIdx {}
+        impl TypeTrait for SaIdx { type Type = u32; }
+        impl CanIndex<SA> for SaIdx {}
+        impl CanIndex<Bwt> for SaIdx {}
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Bwt();
+        impl SeqTrait for Bwt { type Type = u8; }
+    }
+    use types::*;
+
+    type Sa = Permutation<Val<SaIdx>, Val<TextIdx>>;
+
+    #[test]
+    fn test_sa_and_isa() {
+        let text: IdxVec<Text> = b"mississippi".to_vec().into();
+        let sa = Sa::new::<SA>(suffix_array(&text)).unwrap();
+        let isa = sa.inverse();
+        for r in 0..sa.len() {
+            let r: Val<SaIdx> = r.into();
+            assert_eq!(r, isa[sa[r]]);
+        }
+        // "i" is the first suffix, "mississippi" the fifth
+        assert_eq!(Val::<SaIdx>(0), isa[Val::<TextIdx>(10)]);
+        assert_eq!(Val::<SaIdx>(4), isa[Val::<TextIdx>(0)]);
+
+        let id = sa.compose(&isa);
+        assert_eq!(
+            Permutation::<Val<SaIdx>, Val<SaIdx>>::identity(11).as_slice(),
+            id.as_slice()
+        );
+
+        // Text characters in suffix order are the first column of the BWT matrix
+        let first: IdxVec<Bwt> = sa.apply(text);
+        assert_eq!(b"iiiimppssss", first.0.as_slice());
+
+        let sa: IdxVec<SA> = sa.into_seq();
+        assert_eq!(Val::<TextIdx>(10), sa[Val::<SaIdx>(0)]);
+    }
+
+    #[test]
+    fn test_sorting_and_apply() {
+        let x: IdxVec<Text> = vec![5, 3, 9, 3, 0, 7, 1].into();
+        let p = Permutation::<Val<SaIdx>, Val<TextIdx>>::sorting(&x);
+        // Equal values keep their order
+        assert_eq!(
+            &[4, 6, 1, 3, 0, 5, 2],
+            p.as_slice()
+                .iter()
+                .map(|i| i.0)
+                .collect::<Vec<_>>()
+                .as_slice()
+        );
+        let sorted: IdxVec<Bwt> = p.apply(x);
+        assert_eq!(vec![0, 1, 3, 3, 5, 7, 9], sorted.0);
+
+        // Applying the inverse undoes it
+        let back: IdxVec<Text> = p.inverse().apply(sorted);
+        assert_eq!(vec![5, 3, 9, 3, 0, 7, 1], back.0);
+
+        let empty: IdxVec<Text> = vec![].into();
+        assert!(Permutation::<Val<SaIdx>, Val<TextIdx>>::sorting(&empty).is_empty());
+    }
+
+    #[test]
+    fn test_validation() {
+        let ok: IdxVec<SA> = vec![Val(2), Val(0), Val(1)].into();
+        assert!(Sa::new(ok).is_ok());
+        let out: IdxVec<SA> = vec![Val(2), Val(3), Val(1)].into();
+        assert_eq!(
+            PermutationError::OutOfRange {
+                pos: 1,
+                value: 3,
+                len: 3
+            },
+            Sa::new(out).unwrap_err()
+        );
+        let rep: IdxVec<SA> = vec![Val(2), Val(0), Val(2)].into();
+        assert_eq!(
+            PermutationError::Repeated {
+                value: 2,
+                first: 0,
+                second: 2
+            },
+            Sa::new(rep).unwrap_err()
+        );
+    }
+}
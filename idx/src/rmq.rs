@@ -1,4 +1,6 @@
-/*
+use crate::*;
+use std::ops::Range;
+use std::simd::prelude::*;
 
 /// Tests if x is a power of two, x=2^k.
 pub fn power_of_two(x: usize) -> bool {
@@ -121,7 +123,7 @@ pub fn round_down(n: usize, bs: usize) -> (BlockIdx, usize) {
 /// rounded down, and r*bs is n adjusted upwards to the
 /// closest multiple of bs.
 pub fn round_up(n: usize, bs: usize) -> (BlockIdx, usize) {
-    let r = (n + bs - 1) / bs;
+    let r = n.div_ceil(bs);
     (BlockIdx(r), r * bs)
 }
 
@@ -235,47 +237,25 @@ pub fn adjusted_index(i: usize, j: usize) -> (Pow, usize) {
     (k, j - k.value())
 }
 
-/// A rather simple 2D array made from vectors of vectors.
-/// There are better solutions, but I can implement those later
-/// with the same interface.
-pub struct TwoD {
-    table: Vec<Vec<usize>>,
-}
-
-impl TwoD {
-    pub fn new(n: usize) -> TwoD {
-        let Pow(logn) = log_table_size(n);
-        let table = vec![vec![0; logn]; n];
-        TwoD { table }
-    }
-}
-
-impl std::ops::Index<(usize, Pow)> for TwoD {
-    type Output = usize;
-    fn index(&self, index: (usize, Pow)) -> &Self::Output {
-        match index {
-            (i, Pow(k)) => &self.table[i][k],
-        }
-    }
-}
-
-impl std::ops::IndexMut<(usize, Pow)> for TwoD {
-    fn index_mut(&mut self, index: (usize, Pow)) -> &mut Self::Output {
-        match index {
-            (i, Pow(k)) => &mut self.table[i][k],
-        }
+impl TableIndex for Pow {
+    #[inline]
+    fn table_index(self) -> usize {
+        self.0
     }
 }
 
-impl std::fmt::Display for TwoD {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        for row in &self.table {
-            for val in row {
-                let _ = write!(f, "{} ", val);
-            }
-            let _ = write!(f, "\n");
-        }
-        Ok(())
+/// The tables we use for sparse tables: tbl[i,Pow(k)] holds a value for
+/// the range [i,i+2^k). In the RMQ tables the value is a position, and C
+/// lets us pick a narrower integer than usize when the sequence is short
+/// enough for it.
+pub type TwoD<C = usize, S = Vec<C>> = Table<usize, Pow, C, S>;
+
+/// A TwoD table with room for all the powers we need for n positions.
+pub fn two_d<C: Default + Clone>(n: usize) -> TwoD<C> {
+    match n {
+        // log_table_size(0) isn't defined, but we need no powers anyway
+        0 => TwoD::new(0, 0),
+        _ => TwoD::new(n, log_table_size(n).0),
     }
 }
 
@@ -334,7 +314,7 @@ mod tests_2d_table {
     #[test]
     fn test_2d() {
         let n = 5;
-        let mut tbl = TwoD::new(n);
+        let mut tbl: TwoD = two_d(n);
         println!("{}", tbl);
 
         for i in 0..n {
@@ -401,162 +381,988 @@ where
     }
 }
 
-use super::index::*;
+/// The order we use when we pick the "smallest" value in a range. The
+/// RMQ structures find the first value according to this order, so with
+/// Smallest we get range minimum queries, with Largest we get range maximum
+/// queries, and with a closure Fn(&T, &T) -> Ordering we can use any
+/// comparator we like.
+///
+/// If two values compare equal, we pick the left-most of them, unless
+/// rightmost() says otherwise. Wrap an order in Rightmost to flip that.
+pub trait Order<T> {
+    fn cmp(&self, a: &T, b: &T) -> std::cmp::Ordering;
+
+    /// Should ties go to the right-most position rather than the left-most?
+    #[inline]
+    fn rightmost(&self) -> bool {
+        false
+    }
+}
+
+/// The natural order on the values; gives us range minimum queries.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Smallest;
+
+/// The reverse of the natural order; gives us range maximum queries.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Largest;
+
+/// Wraps an order so ties go to the right-most position.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Rightmost<O>(pub O);
+
+impl<T: Ord> Order<T> for Smallest {
+    #[inline]
+    fn cmp(&self, a: &T, b: &T) -> std::cmp::Ordering {
+        a.cmp(b)
+    }
+}
+
+impl<T: Ord> Order<T> for Largest {
+    #[inline]
+    fn cmp(&self, a: &T, b: &T) -> std::cmp::Ordering {
+        b.cmp(a)
+    }
+}
+
+impl<T, O: Order<T>> Order<T> for Rightmost<O> {
+    #[inline]
+    fn cmp(&self, a: &T, b: &T) -> std::cmp::Ordering {
+        self.0.cmp(a, b)
+    }
+    #[inline]
+    fn rightmost(&self) -> bool {
+        !self.0.rightmost()
+    }
+}
+
+impl<T, F> Order<T> for F
+where
+    F: Fn(&T, &T) -> std::cmp::Ordering,
+{
+    #[inline]
+    fn cmp(&self, a: &T, b: &T) -> std::cmp::Ordering {
+        self(a, b)
+    }
+}
+
+/// Does b, at a position to the right of a, win over a in the order?
+#[inline]
+pub(crate) fn right_wins<T, O: Order<T>>(order: &O, a: &T, b: &T) -> bool {
+    use std::cmp::Ordering::*;
+    match order.cmp(b, a) {
+        Less => true,
+        Equal => order.rightmost(),
+        Greater => false,
+    }
+}
 
 /// A point is an index with the corresponding value
-#[derive(Clone, Copy)]
-pub struct Point(pub Idx, pub u32);
+#[derive(Clone, Copy, Debug)]
+pub struct Point<Idx, T>(pub Idx, pub T);
 
-impl Point {
+impl<Idx: Copy, T: Copy> Point<Idx, T> {
     #[inline]
     pub fn idx(&self) -> Idx {
         self.0
     }
     #[inline]
-    pub fn val(&self) -> u32 {
+    pub fn val(&self) -> T {
         self.1
     }
 
-    pub fn new(i: Idx, x: &[u32]) -> Point {
-        Point(i, x[i.0]) // FIXME
+    pub fn new<_Tag>(i: Idx, x: &IdxSlice<_Tag>) -> Point<Idx, T>
+    where
+        _Tag: SeqTrait<Type = T>,
+        Idx: IndexType + CanIndex<_Tag>,
+    {
+        Point(i, x[i])
+    }
+}
+
+/// Pick the first of two points according to order, taking their
+/// positions into account when the values are tied.
+#[inline]
+pub fn first<Idx, T, O>(order: &O, p1: Point<Idx, T>, p2: Point<Idx, T>) -> Point<Idx, T>
+where
+    Idx: IndexType + Copy,
+    T: Copy,
+    O: Order<T>,
+{
+    if p1.idx().index() > p2.idx().index() {
+        // The choice is symmetric, so if p2 has the smallest index,
+        // we flip the points.
+        return first(order, p2, p1);
+    }
+    match right_wins(order, &p1.1, &p2.1) {
+        true => p2,
+        false => p1,
     }
 }
 
-impl Min for Point {
+impl<Idx, T> Min for Point<Idx, T>
+where
+    Idx: IndexType + Copy,
+    T: Ord + Copy,
+{
     #[inline]
-    fn min(p1: Point, p2: Point) -> Point {
-        if p1.idx() > p2.idx() {
-            // min is symmetric, giving preference to the smallest index,
-            // so if p2 has the smallest index, we flip the points.
-            return Self::min(p2, p1);
-        }
-        // Pick the smallest value, but in case of ties, pick p1.
-        match p1.val() <= p2.val() {
-            true => p1,
-            false => p2,
-        }
+    fn min(p1: Self, p2: Self) -> Self {
+        // Pick the smallest value, but in case of ties, pick the left-most.
+        first(&Smallest, p1, p2)
     }
 }
 
-impl std::fmt::Display for Point {
+impl<Idx, T> std::fmt::Display for Point<Idx, T>
+where
+    Idx: std::fmt::Display,
+    T: std::fmt::Display,
+{
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "Point({},{})", self.0, self.1)
     }
 }
 
+/// Interface shared by the range minimum query structures. Given a
+/// non-empty range [i,j), they find the position of the first value in
+/// it, according to the structure's Order, as a point with the index type
+/// of the query. With the default order, Smallest, that is the left-most
+/// minimal value.
+pub trait RangeMinQuery<_Tag>: Sized
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Copy,
+{
+    type Order: Order<_Tag::Type>;
+
+    /// Build the structure over values, using order to compare them.
+    fn build(values: IdxVec<_Tag>, order: Self::Order) -> Self;
+
+    /// Build the structure from a copy of the values in a slice.
+    fn from_slice(values: &IdxSlice<_Tag>, order: Self::Order) -> Self {
+        Self::build(values.as_slice().to_vec().into(), order)
+    }
+
+    /// The values the structure was built from.
+    fn values(&self) -> &IdxSlice<_Tag>;
+
+    /// The order the structure uses.
+    fn order(&self) -> &Self::Order;
+
+    /// Get the first point in the range r. The range must be non-empty.
+    fn rmq<Idx>(&self, r: Range<Idx>) -> Point<Idx, _Tag::Type>
+    where
+        Idx: IndexType + CanIndex<_Tag> + Copy;
+
+    /// Answer a batch of queries, with the results in the same order as
+    /// the queries. We handle the queries grouped by where they start,
+    /// with a counting sort, so the short ranges, which we scan directly
+    /// instead of going through the structure, are visited in order along
+    /// the values. The results are the same as calling rmq() on each query.
+    fn rmq_many<Idx>(&self, queries: &[Range<Idx>]) -> Vec<Point<Idx, _Tag::Type>>
+    where
+        Idx: IndexType + CanIndex<_Tag> + Copy,
+    {
+        let x = self.values().as_slice();
+        let groups = x.len() / QUERY_GROUP + 1;
+        // Bad ranges go in the last group; checked_range rejects them there.
+        let group = |r: &Range<Idx>| usize::min(r.start.index() / QUERY_GROUP, groups - 1);
+        let mut ends = vec![0; groups];
+        for r in queries {
+            ends[group(r)] += 1;
+        }
+        for g in 1..groups {
+            ends[g] += ends[g - 1];
+        }
+        let mut grouped = vec![0; queries.len()];
+        for (q, r) in queries.iter().enumerate().rev() {
+            let g = group(r);
+            ends[g] -= 1;
+            grouped[ends[g]] = q;
+        }
+
+        let mut res = vec![None; queries.len()];
+        for q in grouped {
+            let (i, j) = checked_range(&queries[q], x.len());
+            res[q] = Some(if j - i <= SHORT_RANGE {
+                let m = self.order().short_scan(x, i, j);
+                Point(Idx::from_index(m), x[m])
+            } else {
+                self.rmq(queries[q].clone())
+            });
+        }
+        res.into_iter().map(Option::unwrap).collect()
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.values().len()
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.values().is_empty()
+    }
+}
+
+// Checks a query range and gets the raw positions out of it.
+#[inline]
+pub(crate) fn checked_range<Idx: IndexType + Copy>(r: &Range<Idx>, n: usize) -> (usize, usize) {
+    let (i, j) = (r.start.index(), r.end.index());
+    assert!(i < j && j <= n, "rmq needs a non-empty range");
+    (i, j)
+}
+
+/// Finds the position of the first value in x[i..j) according to
+/// order, by scanning through the range.
+fn scan<T, O: Order<T>>(order: &O, x: &[T], i: usize, j: usize) -> usize {
+    let mut m = i;
+    for k in i + 1..j {
+        if right_wins(order, &x[m], &x[k]) {
+            m = k;
+        }
+    }
+    m
+}
+
+// rmq_many handles queries that start within the same this many values
+// together.
+const QUERY_GROUP: usize = 4096;
+
+// Number of interleaved sub-sequences in lane_scan.
+const LANES: usize = 8;
+
+// Ranges up to this length are scanned rather than looked up in the
+// structure when we answer queries in batches. In benches/rmq_many.rs,
+// with u32 values, scanning beats BlockRMQImpl lookups up to about 32
+// values when they are in the cache and 64 when they aren't, but
+// PowerRMQImpl lookups only up to 8 or 16, so this favours the default,
+// BlockRMQImpl.
+const SHORT_RANGE: usize = 32;
+
+/// Same as scan, but in a single pass that keeps a running first value
+/// and position for each of LANES interleaved sub-sequences, so the
+/// comparisons in one step don't depend on each other. We combine the
+/// lanes with first(), which takes positions into account, so the result
+/// is the same as scan's. This is the scan for any values and orders;
+/// ShortScan picks an explicit SIMD scan where there is one.
+fn lane_scan<T: Copy, O: Order<T>>(order: &O, x: &[T], i: usize, j: usize) -> usize {
+    if j - i < 2 * LANES {
+        return scan(order, x, i, j);
+    }
+    let mut vals: [T; LANES] = std::array::from_fn(|l| x[i + l]);
+    let mut idxs: [usize; LANES] = std::array::from_fn(|l| i + l);
+    let chunks = x[i + LANES..j].chunks_exact(LANES);
+    let tail = chunks.remainder();
+    for (c, chunk) in chunks.enumerate() {
+        let base = i + LANES * (c + 1);
+        for l in 0..LANES {
+            let wins = right_wins(order, &vals[l], &chunk[l]);
+            vals[l] = if wins { chunk[l] } else { vals[l] };
+            idxs[l] = if wins { base + l } else { idxs[l] };
+        }
+    }
+    let mut best = Point(idxs[0], vals[0]);
+    for l in 1..LANES {
+        best = first(order, best, Point(idxs[l], vals[l]));
+    }
+    let tail_start = j - tail.len();
+    for (k, y) in tail.iter().enumerate() {
+        best = first(order, best, Point(tail_start + k, *y));
+    }
+    best.idx()
+}
+
+/// Primitive integers we can scan with std::simd, as many at a time as
+/// fit in 256 bits.
+trait SimdScan: Copy + Ord {
+    /// The position of the left-most smallest, or largest, value in x.
+    fn simd_scan(x: &[Self], largest: bool) -> usize;
+}
+
+// We scan twice: once for the best value, with a vector min or max per
+// chunk, and once for the first chunk holding it, which usually stops
+// early. Keeping positions in the lanes in a single pass needs a select
+// per chunk on top of the comparison, and without SSE4.1 that is slower
+// than the second pass (see benches/rmq_many.rs).
+macro_rules! simd_scan {
+    ($t:ty, $lanes:expr) => {
+        impl SimdScan for $t {
+            fn simd_scan(x: &[$t], largest: bool) -> usize {
+                type V = Simd<$t, $lanes>;
+                if x.len() < $lanes {
+                    let order = |a: &$t, b: &$t| if largest { b.cmp(a) } else { a.cmp(b) };
+                    return scan(&order, x, 0, x.len());
+                }
+                let chunks = x.chunks_exact($lanes);
+                let tail = chunks.remainder();
+                let mut best = V::from_slice(&x[..$lanes]);
+                for c in chunks.clone().skip(1) {
+                    let v = V::from_slice(c);
+                    best = if largest {
+                        best.simd_max(v)
+                    } else {
+                        best.simd_min(v)
+                    };
+                }
+                let mut m = if largest {
+                    best.reduce_max()
+                } else {
+                    best.reduce_min()
+                };
+                for &y in tail {
+                    m = if largest { m.max(y) } else { m.min(y) };
+                }
+
+                let target = V::splat(m);
+                for (c, chunk) in chunks.enumerate() {
+                    if let Some(l) = V::from_slice(chunk).simd_eq(target).first_set() {
+                        return c * $lanes + l;
+                    }
+                }
+                let start = x.len() - tail.len();
+                start + tail.iter().position(|&y| y == m).unwrap()
+            }
+        }
+
+        impl ShortScan<$t> for Smallest {
+            #[inline]
+            fn short_scan(&self, x: &[$t], i: usize, j: usize) -> usize {
+                i + <$t>::simd_scan(&x[i..j], false)
+            }
+        }
+
+        impl ShortScan<$t> for Largest {
+            #[inline]
+            fn short_scan(&self, x: &[$t], i: usize, j: usize) -> usize {
+                i + <$t>::simd_scan(&x[i..j], true)
+            }
+        }
+    };
+}
+
+/// The scan rmq_many uses for short ranges. It is lane_scan for all
+/// values and orders, except Smallest and Largest on primitive integers,
+/// where it is the std::simd scan of SimdScan.
+pub(crate) trait ShortScan<T> {
+    fn short_scan(&self, x: &[T], i: usize, j: usize) -> usize;
+}
+
+impl<T: Copy, O: Order<T>> ShortScan<T> for O {
+    #[inline]
+    default fn short_scan(&self, x: &[T], i: usize, j: usize) -> usize {
+        lane_scan(self, x, i, j)
+    }
+}
+
+simd_scan!(i8, 32);
+simd_scan!(u8, 32);
+simd_scan!(i16, 16);
+simd_scan!(u16, 16);
+simd_scan!(i32, 8);
+simd_scan!(u32, 8);
+simd_scan!(i64, 4);
+simd_scan!(u64, 4);
+simd_scan!(isize, 4);
+simd_scan!(usize, 4);
+
+/// RMQ without any preprocessing. Queries scan through the range, so
+/// they take O(j-i) time. This is mostly useful as a reference for testing,
+/// and for very short sequences.
+pub struct NaiveRMQImpl<_Tag: SeqTrait, O = Smallest> {
+    values: IdxVec<_Tag>,
+    order: O,
+}
+
+impl<_Tag> NaiveRMQImpl<_Tag>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Ord + Copy,
+{
+    pub fn new(values: IdxVec<_Tag>) -> NaiveRMQImpl<_Tag> {
+        Self::with_order(values, Smallest)
+    }
+}
+
+impl<_Tag, O> NaiveRMQImpl<_Tag, O>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Copy,
+    O: Order<_Tag::Type>,
+{
+    pub fn with_order(values: IdxVec<_Tag>, order: O) -> NaiveRMQImpl<_Tag, O> {
+        NaiveRMQImpl { values, order }
+    }
+}
+
+impl<_Tag, O> RangeMinQuery<_Tag> for NaiveRMQImpl<_Tag, O>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Copy,
+    O: Order<_Tag::Type>,
+{
+    type Order = O;
+
+    fn build(values: IdxVec<_Tag>, order: O) -> Self {
+        Self::with_order(values, order)
+    }
+
+    #[inline]
+    fn values(&self) -> &IdxSlice<_Tag> {
+        &self.values
+    }
+
+    #[inline]
+    fn order(&self) -> &O {
+        &self.order
+    }
+
+    fn rmq<Idx>(&self, r: Range<Idx>) -> Point<Idx, _Tag::Type>
+    where
+        Idx: IndexType + CanIndex<_Tag> + Copy,
+    {
+        let (i, j) = checked_range(&r, self.len());
+        let x = self.values.as_slice();
+        let m = scan(&self.order, x, i, j);
+        Point(Idx::from_index(m), x[m])
+    }
+}
+
+/// Builds the sparse table for n positions with values val(i), where
+/// tbl[i,Pow(k)] contains the position of the first value, according
+/// to order, in the range [i,i+2^k). The positions are stored as C, so
+/// n must fit in C.
+fn power_table<T, O, F, C>(n: usize, order: &O, val: F) -> TwoD<C>
+where
+    T: Copy,
+    O: Order<T>,
+    F: Fn(usize) -> T,
+    C: IndexType + Copy + Default,
+{
+    let mut tbl = two_d(n);
+    if n == 0 {
+        // Nothing to tabulate
+        return tbl;
+    }
+    let logn = tbl.cols();
+
+    // Base case: intervals [i,i+1) = [i,i+2^0).
+    for i in 0..n {
+        tbl[(i, Pow(0))] = C::from_index(i);
+    }
+
+    // Dynamic programming construction of tables of increasing length.
+    // We have O(log n) runs of the outer loop and O(n) of the inner,
+    // so the total time is O(n log n).
+    for k in 1..logn {
+        let half = Pow(k - 1).value();
+        for i in 0..=(n - Pow(k).value()) {
+            // Interval [i,i+2^k) = [i,i+2^{k-1}) [i+2^{k-1},(i+2^{k-1})+2^{k-1})
+            let left = tbl[(i, Pow(k - 1))].index();
+            let right = tbl[(i + half, Pow(k - 1))].index();
+            let Point(m, _) = first(order, Point(left, val(left)), Point(right, val(right)));
+            tbl[(i, Pow(k))] = C::from_index(m);
+        }
+    }
+    tbl
+}
+
+/// Looks up the position of the first value in [i,j) in a table
+/// built with power_table().
+#[inline]
+pub(crate) fn power_query<T, O, F, C, S>(tbl: &TwoD<C, S>, order: &O, val: F, i: usize, j: usize) -> usize
+where
+    T: Copy,
+    O: Order<T>,
+    F: Fn(usize) -> T,
+    C: IndexType + Copy,
+    S: AsRef<[C]>,
+{
+    // Work out k so [i,2^k) and [j-2^k,j) are overlapping (and are not overlapping)
+    // anything outside of [i,j). Then use the table to get the index with the smallest
+    // lcp in those intervals, and pick the smaller of the two (with the first index
+    // in case of a tie). All in O(1).
+    let (p, ii) = adjusted_index(i, j);
+    let (left, right) = (tbl[(i, p)].index(), tbl[(ii, p)].index());
+    let Point(m, _) = first(order, Point(left, val(left)), Point(right, val(right)));
+    m
+}
+
 /// RMQ table that tabulates all [i,i+2^k] ranges (there are O(n log n)),
 /// form which we can get the RMQ from the table by splitting [i,j) into
 /// two, [i,2^k) and [j-2^k,j) (where k is the largest such k). We can get
 /// the RMQ from those two intervals with a table lookup in O(1) and then
 /// pick the one of those with the smallest value.
 /// The result is O(n log n) preprocessing and O(1) lookup.
-pub struct PowerRMQImpl {
-    lcp: Vec<u32>,
-    tbl: TwoD,
+///
+/// The table is built from an IdxVec<_Tag> and queried with any index
+/// type that can index _Tag, and the points we get back carry that index
+/// type with them. The table stores positions as C, so for sequences
+/// shorter than 2^32 we can halve its size with C = u32.
+pub struct PowerRMQImpl<_Tag: SeqTrait, O = Smallest, C = usize> {
+    pub(crate) values: IdxVec<_Tag>,
+    pub(crate) order: O,
+    pub(crate) tbl: TwoD<C>,
 }
 
-impl PowerRMQImpl {
-    fn point(&self, i: Idx) -> Point {
-        Point(i, self.lcp[i])
+impl<_Tag> PowerRMQImpl<_Tag>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Ord + Copy,
+{
+    pub fn new(values: IdxVec<_Tag>) -> PowerRMQImpl<_Tag> {
+        Self::with_order(values, Smallest)
     }
-    fn new(lcp: Vec<u32>) -> PowerRMQImpl {
-        let n = lcp.len();
+}
 
-        // When tbl is a TwoD table, interpret tbl[i,Pow(k)] as containing
-        // values (at powers of two) in the range [i,i+2^k).
-        let Pow(logn) = log_table_size(n);
-        let mut tbl = TwoD::new(n);
+impl<_Tag, O> PowerRMQImpl<_Tag, O>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Copy,
+    O: Order<_Tag::Type>,
+{
+    pub fn with_order(values: IdxVec<_Tag>, order: O) -> PowerRMQImpl<_Tag, O> {
+        Self::build(values, order)
+    }
+}
 
-        // Base case: intervals [i,i+1) = [i,i+2^0).
-        for i in 0..n {
-            tbl[(i, Pow(0))] = i;
-        }
-
-        // Dynamic programming construction of tables of increasing length.
-        // We have O(log n) runs of the outer loop and O(n) of the inner,
-        // so the total time is O(n log n).
-        for k in 1..logn {
-            for i in 0..(n - Pow(k - 1).value()) {
-                // Interval [i,i+2^k) = [i,i+2^{k-1}) [i+2^{k-1},(i+2^{k-1})+2^{k-1})
-                let left = Point::new(Idx(tbl[(i, Pow(k - 1))]), &lcp);
-                let right = Point::new(Idx(tbl[(i + Pow(k - 1).value(), Pow(k - 1))]), &lcp);
-                let Point(Idx(m), _) = min(left, right); // FIXME
-                tbl[(i, Pow(k))] = m;
+impl<_Tag, O, C> RangeMinQuery<_Tag> for PowerRMQImpl<_Tag, O, C>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Copy,
+    O: Order<_Tag::Type>,
+    C: IndexType + Copy + Default,
+{
+    type Order = O;
+
+    fn build(values: IdxVec<_Tag>, order: O) -> Self {
+        let x = values.as_slice();
+        let tbl = power_table(x.len(), &order, |i| x[i]);
+        PowerRMQImpl { values, order, tbl }
+    }
+
+    #[inline]
+    fn values(&self) -> &IdxSlice<_Tag> {
+        &self.values
+    }
+
+    #[inline]
+    fn order(&self) -> &O {
+        &self.order
+    }
+
+    fn rmq<Idx>(&self, r: Range<Idx>) -> Point<Idx, _Tag::Type>
+    where
+        Idx: IndexType + CanIndex<_Tag> + Copy,
+    {
+        let (i, j) = checked_range(&r, self.len());
+        let x = self.values.as_slice();
+        let m = power_query(&self.tbl, &self.order, |i| x[i], i, j);
+        Point(Idx::from_index(m), x[m])
+    }
+}
+
+/// Signature of the Cartesian tree of a block. Blocks with the same
+/// signature have their first values at the same offsets for all
+/// sub-ranges, so they can share a lookup table. The signature is the
+/// sequence of pushes (1) and pops (0) we do when we build the tree with
+/// a stack, prefixed with a 1 so blocks of different lengths get different
+/// signatures. For a block of length b it takes up at most 2b+1 bits.
+fn block_signature<T, O: Order<T>>(order: &O, block: &[T]) -> u64 {
+    let mut stack: Vec<&T> = Vec::with_capacity(block.len());
+    let mut sig = 1u64;
+    for x in block {
+        // We only pop the values x wins over, so with left-most ties
+        // equal values stay to the left of x in the tree.
+        while matches!(stack.last(), Some(&top) if right_wins(order, top, x)) {
+            stack.pop();
+            sig <<= 1;
+        }
+        stack.push(x);
+        sig = (sig << 1) | 1;
+    }
+    sig
+}
+
+/// Tabulates the offset of the first value for all ranges [i,j] in a
+/// block of at most bs values, as tbl[i * bs + j]. We append the table
+/// to tables, so all the tables live in one contiguous vector.
+fn block_table<T, O: Order<T>>(order: &O, block: &[T], bs: usize, tables: &mut Vec<u8>) {
+    let start = tables.len();
+    tables.resize(start + bs * bs, 0);
+    let tbl = &mut tables[start..];
+    for i in 0..block.len() {
+        let mut m = i;
+        for j in i..block.len() {
+            if right_wins(order, &block[m], &block[j]) {
+                m = j;
             }
+            tbl[i * bs + j] = m as u8;
         }
-        PowerRMQImpl { lcp, tbl }
     }
-    fn len(&self) -> usize {
-        self.lcp.len()
+}
+
+/// The largest block size we support. The Cartesian tree signatures need
+/// 2*bs+1 bits and must fit in a u64.
+pub const MAX_BLOCK_SIZE: usize = 31;
+
+/// Linear space, constant time, RMQ (Fischer and Heun). The values are split
+/// into blocks of size bs ~ log(n)/4. We keep a sparse table over the block
+/// minima, which takes O(n/bs log(n/bs)) = O(n) space, and for the ranges
+/// inside blocks we use lookup tables shared between blocks with the same
+/// Cartesian tree. There are at most 4^bs = O(sqrt(n)) of those, each of size
+/// bs^2, so all in all we use O(n) space and still answer queries in O(1).
+///
+/// A query [i,j) that spans more than one block is split into the suffix of
+/// i's block, the blocks strictly between, and the prefix of j's block, and
+/// we take the first of the three.
+///
+/// Positions and table numbers are stored as C, which can be narrower
+/// than usize if the sequence is short enough.
+pub struct BlockRMQImpl<_Tag: SeqTrait, O = Smallest, C = usize> {
+    pub(crate) values: IdxVec<_Tag>,
+    pub(crate) order: O,
+    pub(crate) bs: usize,
+    // Position (into values) of the first value in each block
+    pub(crate) block_min: Vec<C>,
+    // Sparse table over the block minima
+    pub(crate) block_tbl: TwoD<C>,
+    // Which in-block table each block uses
+    pub(crate) block_type: Vec<C>,
+    // In-block tables, one row per Cartesian tree signature, holding
+    // the offset for [i,j] in column i * bs + j.
+    pub(crate) tables: Table<usize, usize, u8>,
+}
+
+impl<_Tag> BlockRMQImpl<_Tag>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Ord + Copy,
+{
+    pub fn new(values: IdxVec<_Tag>) -> BlockRMQImpl<_Tag> {
+        Self::with_order(values, Smallest)
     }
-    fn rmq(&self, i: usize, j: usize) -> Point {
-        // Work out k so [i,2^k) and [j-2^k,j) are overlapping (and are not overlapping)
-        // anything outside of [i,j). Then use the table to get the index with the smallest
-        // lcp in those intervals, and pick the smaller of the two (with the first index
-        // in case of a tie). All in O(1).
-        let (p, ii) = adjusted_index(i, j);
-        min(
-            self.point(Idx(self.tbl[(i, p)])),
-            self.point(Idx(self.tbl[(ii, p)])),
-        )
+
+    /// Build the RMQ with a given block size, 1 <= bs <= MAX_BLOCK_SIZE.
+    pub fn with_block_size(values: IdxVec<_Tag>, bs: usize) -> BlockRMQImpl<_Tag> {
+        Self::with_order_and_block_size(values, Smallest, bs)
+    }
+}
+
+impl<_Tag, O> BlockRMQImpl<_Tag, O>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Copy,
+    O: Order<_Tag::Type>,
+{
+    pub fn with_order(values: IdxVec<_Tag>, order: O) -> BlockRMQImpl<_Tag, O> {
+        Self::build(values, order)
+    }
+
+    /// Build the RMQ with a given order and block size, 1 <= bs <= MAX_BLOCK_SIZE.
+    pub fn with_order_and_block_size(
+        values: IdxVec<_Tag>,
+        order: O,
+        bs: usize,
+    ) -> BlockRMQImpl<_Tag, O> {
+        Self::build_with_block_size(values, order, bs)
+    }
+}
+
+impl<_Tag, O, C> BlockRMQImpl<_Tag, O, C>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Copy,
+    O: Order<_Tag::Type>,
+    C: IndexType + Copy + Default,
+{
+    // The block size we use for n values, ~ log(n)/4.
+    fn default_block_size(n: usize) -> usize {
+        let bs = if n == 0 { 1 } else { log2_down(n).0 / 4 };
+        bs.clamp(1, MAX_BLOCK_SIZE)
+    }
+
+    /// Build the RMQ with a given order and block size, 1 <= bs <= MAX_BLOCK_SIZE,
+    /// and with any cell type.
+    pub fn build_with_block_size(
+        values: IdxVec<_Tag>,
+        order: O,
+        bs: usize,
+    ) -> BlockRMQImpl<_Tag, O, C> {
+        assert!(
+            (1..=MAX_BLOCK_SIZE).contains(&bs),
+            "block size must be between 1 and {}",
+            MAX_BLOCK_SIZE
+        );
+        let x = values.as_slice();
+
+        let mut block_min = Vec::new();
+        let mut block_type = Vec::new();
+        let mut tables = Vec::new();
+        let mut types = std::collections::HashMap::new();
+        for (b, block) in x.chunks(bs).enumerate() {
+            let tbl_idx = *types
+                .entry(block_signature(&order, block))
+                .or_insert_with(|| {
+                    block_table(&order, block, bs, &mut tables);
+                    tables.len() / (bs * bs) - 1
+                });
+            block_type.push(C::from_index(tbl_idx));
+            let m = tables[tbl_idx * bs * bs + block.len() - 1] as usize;
+            block_min.push(C::from_index(b * bs + m));
+        }
+        let block_tbl = power_table(block_min.len(), &order, |b| x[block_min[b].index()]);
+        let tables = Table::from_vec(types.len(), bs * bs, tables);
+
+        BlockRMQImpl {
+            values,
+            order,
+            bs,
+            block_min,
+            block_tbl,
+            block_type,
+            tables,
+        }
+    }
+
+    /// The block size the RMQ uses.
+    #[inline]
+    pub fn block_size(&self) -> usize {
+        self.bs
+    }
+
+    // Borrowed view of the tables, for the queries.
+    #[inline]
+    fn parts(&self) -> BlockParts<'_, _Tag::Type, C> {
+        BlockParts {
+            values: self.values.as_slice(),
+            bs: self.bs,
+            block_min: &self.block_min,
+            block_tbl: self.block_tbl.view(),
+            block_type: &self.block_type,
+            tables: self.tables.view(),
+        }
+    }
+}
+
+/// The tables of a BlockRMQImpl, borrowed from wherever they live, so
+/// we can run the queries on tables we didn't build ourselves.
+pub(crate) struct BlockParts<'a, T, C> {
+    pub(crate) values: &'a [T],
+    pub(crate) bs: usize,
+    pub(crate) block_min: &'a [C],
+    pub(crate) block_tbl: TwoD<C, &'a [C]>,
+    pub(crate) block_type: &'a [C],
+    pub(crate) tables: Table<usize, usize, u8, &'a [u8]>,
+}
+
+impl<T: Copy, C: IndexType + Copy> BlockParts<'_, T, C> {
+    // Position of the first value in [i,j], where both are in block b.
+    #[inline]
+    fn in_block(&self, BlockIdx(b): BlockIdx, i: usize, j: usize) -> Point<usize, T> {
+        let start = b * self.bs;
+        let tbl = self.block_type[b].index();
+        let m = start + self.tables[(tbl, (i - start) * self.bs + (j - start))] as usize;
+        Point(m, self.values[m])
+    }
+
+    /// Position of the first value in [i,j), which must be a non-empty
+    /// range inside the values.
+    pub(crate) fn query<O: Order<T>>(&self, order: &O, i: usize, j: usize) -> usize {
+        // Blocks for the first and last position in the range. We work
+        // with the closed interval [i,j-1] here.
+        let (bi, _) = round_down(i, self.bs);
+        let (bj, bj_start) = round_down(j - 1, self.bs);
+        if bi == bj {
+            return self.in_block(bi, i, j - 1).idx();
+        }
+
+        let (next, next_start) = round_up(i + 1, self.bs);
+        let left = self.in_block(bi, i, next_start - 1);
+        let right = self.in_block(bj, bj_start, j - 1);
+        if next == bj {
+            return first(order, left, right).idx();
+        }
+
+        let x = self.values;
+        let block_val = |b: usize| x[self.block_min[b].index()];
+        let b = power_query(&self.block_tbl, order, block_val, next.0, bj.0);
+        let m = self.block_min[b].index();
+        first(order, first(order, left, Point(m, x[m])), right).idx()
+    }
+}
+
+impl<_Tag, O, C> RangeMinQuery<_Tag> for BlockRMQImpl<_Tag, O, C>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Copy,
+    O: Order<_Tag::Type>,
+    C: IndexType + Copy + Default,
+{
+    type Order = O;
+
+    fn build(values: IdxVec<_Tag>, order: O) -> Self {
+        let bs = Self::default_block_size(values.len());
+        Self::build_with_block_size(values, order, bs)
+    }
+
+    #[inline]
+    fn values(&self) -> &IdxSlice<_Tag> {
+        &self.values
+    }
+
+    #[inline]
+    fn order(&self) -> &O {
+        &self.order
+    }
+
+    fn rmq<Idx>(&self, r: Range<Idx>) -> Point<Idx, _Tag::Type>
+    where
+        Idx: IndexType + CanIndex<_Tag> + Copy,
+    {
+        let (i, j) = checked_range(&r, self.len());
+        let m = self.parts().query(&self.order, i, j);
+        Point(Idx::from_index(m), self.values.as_slice()[m])
     }
 }
 
 /// Finds the left-most index with the smallest value in x.
 /// Returns the index of the left-most minimal value and the
 /// minimal value. If [i,j) is empty, you get None.
-pub fn smallest_in_range(x: &[u32], r: &Range) -> Option<Point> {
-    // We don't need to uses cases here, if we know we have a valid
-    // range, because min() and position() returns None if the interval
-    // is empty.
-    let y = &x[r];
-    let min_val = y.iter().min()?;
-    let pos = r.start + y.iter().position(|a| a == min_val)?;
-    Some(Point(pos, *min_val))
+pub fn smallest_in_range<_Tag, Idx>(
+    x: &IdxSlice<_Tag>,
+    r: Range<Idx>,
+) -> Option<Point<Idx, _Tag::Type>>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Ord + Copy,
+    Idx: IndexType + CanIndex<_Tag> + Copy,
+{
+    let (i, j) = (r.start.index(), r.end.index());
+    if i >= j {
+        return None;
+    }
+    let x = x.as_slice();
+    let m = Smallest.short_scan(x, i, j);
+    Some(Point(Idx::from_index(m), x[m]))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    fn check_min_in_interval(lcp: &[u32], r: &Range) {
-        use Cases2::*;
+    #[rustfmt::skip]
+    mod types {
+        use crate::*;
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Lcp();
+        impl SeqTrait for Lcp { type Type = u32; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct I {}
+        impl TypeTrait for I { type Type = usize; }
+        impl CanIndex<Lcp> for I {}
+    }
+    use types::*;
+
+    fn idx(i: usize) -> Val<I> {
+        i.into()
+    }
+
+    fn check_min_in_interval(lcp: &IdxSlice<Lcp>, i: Val<I>, j: Val<I>) {
         // We only care about non-empty intervals here
-        if let R(i, j) = r.cases2() {
-            let Point(k, _) = smallest_in_range(lcp, r).unwrap();
+        if i < j {
+            let Point(k, v) = smallest_in_range(lcp, i..j).unwrap();
             assert!(i <= k);
             assert!(k < j);
+            assert_eq!(v, lcp[k]);
 
-            let v = lcp[k];
             for l in i..k {
                 assert!(lcp[l] > v);
             }
-            for l in k + 1..j {
+            for l in k..j {
                 assert!(lcp[l] >= v)
             }
+        } else {
+            assert!(smallest_in_range(lcp, i..j).is_none());
         }
     }
 
-    fn check_min(lcp: &[u32]) {
-        for i in Idx(0)..Idx(lcp.len()) {
-            for j in i + 1..Idx(lcp.len() + 1) {
-                check_min_in_interval(lcp, &range(i, j))
+    fn check_min(lcp: &IdxSlice<Lcp>) {
+        let n = lcp.len();
+        for i in 0..n {
+            for j in i..n + 1 {
+                check_min_in_interval(lcp, idx(i), idx(j))
             }
         }
     }
-    fn check_rmq() {
-        // Not power of two
-        let v = vec![2, 1, 2, 5, 3, 6, 1, 3, 7, 4];
+
+    fn check_against_naive<R>(rmq: &R)
+    where
+        R: RangeMinQuery<Lcp>,
+        R::Order: Clone,
+    {
+        let naive = NaiveRMQImpl::from_slice(rmq.values(), rmq.order().clone());
+        let n = rmq.len();
+        for i in 0..n {
+            for j in i + 1..n + 1 {
+                let Point(k1, v1) = naive.rmq(idx(i)..idx(j));
+                let Point(k2, v2) = rmq.rmq(idx(i)..idx(j));
+                assert_eq!(k1, k2);
+                assert_eq!(v1, v2);
+            }
+        }
+    }
+
+    fn check_orders<O: Order<u32> + Clone>(v: &[u32], order: O) {
+        let v: IdxVec<Lcp> = v.to_vec().into();
+        check_against_naive(&PowerRMQImpl::<Lcp, O>::from_slice(&v, order.clone()));
+        check_against_naive(&BlockRMQImpl::<Lcp, O>::from_slice(&v, order.clone()));
+        // Narrower cells give the same answers
+        check_against_naive(&PowerRMQImpl::<Lcp, O, u32>::from_slice(&v, order.clone()));
+        check_against_naive(&BlockRMQImpl::<Lcp, O, u16>::from_slice(&v, order.clone()));
+        for bs in 1..6 {
+            let rmq =
+                BlockRMQImpl::with_order_and_block_size(v.0.clone().into(), order.clone(), bs);
+            check_against_naive(&rmq);
+        }
+    }
+
+    fn check_rmq(lcp: Vec<u32>) {
+        let v: IdxVec<Lcp> = lcp.clone().into();
         check_min(&v);
+        // The naive RMQ should agree with smallest_in_range...
+        let naive = NaiveRMQImpl::new(v);
+        for i in 0..lcp.len() {
+            for j in i + 1..lcp.len() + 1 {
+                let Point(k1, _) = smallest_in_range(naive.values(), idx(i)..idx(j)).unwrap();
+                assert_eq!(k1, naive.rmq(idx(i)..idx(j)).idx());
+            }
+        }
+        // ...and the others with the naive one
+        check_against_naive(&PowerRMQImpl::new(lcp.clone().into()));
+        check_against_naive(&BlockRMQImpl::new(lcp.clone().into()));
+        for bs in 1..6 {
+            check_against_naive(&BlockRMQImpl::with_block_size(lcp.clone().into(), bs));
+        }
+        check_orders(&lcp, Largest);
+        check_orders(&lcp, Rightmost(Smallest));
+        check_orders(&lcp, Rightmost(Largest));
+    }
+
+    #[test]
+    fn test_rmq() {
+        // Not power of two
+        check_rmq(vec![2, 1, 2, 5, 3, 6, 1, 3, 7, 4]);
         // Power of two
-        let v = vec![2, 1, 2, 5, 3, 6, 1, 3, 7, 4, 2, 6, 3, 4, 7, 9];
-        check_min(&v);
+        check_rmq(vec![2, 1, 2, 5, 3, 6, 1, 3, 7, 4, 2, 6, 3, 4, 7, 9]);
         // Not power of two
-        let v = vec![2, 1, 2, 0, 2, 1, 3, 7, 4];
-        check_min(&v);
+        check_rmq(vec![2, 1, 2, 0, 2, 1, 3, 7, 4]);
         // Power of two
-        let v = vec![2, 1, 2, 5, 3, 6, 1, 3];
-        check_min(&v);
+        check_rmq(vec![2, 1, 2, 5, 3, 6, 1, 3]);
+        // Degenerate
+        check_rmq(vec![1]);
+        check_rmq(vec![]);
     }
 
     #[test]
@@ -564,38 +1370,241 @@ mod tests {
         // First a few checks of the Power specific table...
         // can we handle the diagonal (base case of the dynamic programming),
         // and can we handle the cases where we only look up in the table?
-        let v = vec![2, 1, 2, 5, 3, 6, 1, 3, 7, 4, 1, 2, 4, 5, 6, 7];
-        let rmqa = PowerRMQImpl::new(v.clone());
-        println!("{:?}", &v);
+        let v: IdxVec<Lcp> = vec![2, 1, 2, 5, 3, 6, 1, 3, 7, 4, 1, 2, 4, 5, 6, 7].into();
+        let n = v.len();
+        let rmqa = PowerRMQImpl::new(v);
+        println!("{:?}", rmqa.values());
         println!("{}", rmqa.tbl);
-        println!("{}", rmqa.rmq(0, rmqa.len()));
+        println!("{}", rmqa.rmq(idx(0)..idx(rmqa.len())));
 
         // Checking diagonal
-        for i in 0..v.len() {
-            assert_eq!(Idx(i), rmqa.rmq(i, i + 1).idx());
+        for i in 0..n {
+            assert_eq!(idx(i), rmqa.rmq(idx(i)..idx(i + 1)).idx());
         }
 
         // Checking powers
-        for i in 0..v.len() {
+        for i in 0..n {
             for k in [0, 1, 2, 3] {
                 let j = i + (1 << k);
-                if j > v.len() {
+                if j > n {
                     continue;
                 }
-                let i1 = smallest_in_range(&v, &range(Idx(i), Idx(j))).unwrap().idx();
-                let i2 = rmqa.rmq(i, j).idx();
-                println!(
-                    "[{},{}): {}, {}, [offset={}] {:?}",
-                    i,
-                    j,
-                    i1,
-                    i2,
-                    i,
-                    &v[i..j]
-                );
+                let i1 = smallest_in_range(rmqa.values(), idx(i)..idx(j))
+                    .unwrap()
+                    .idx();
+                let i2 = rmqa.rmq(idx(i)..idx(j)).idx();
+                println!("[{},{}): {}, {}", i, j, i1, i2);
                 assert_eq!(i1, i2);
             }
         }
     }
+
+    #[test]
+    fn test_rmq_ties() {
+        // Ties go to the left-most index
+        let v: IdxVec<Lcp> = vec![3, 1, 2, 1, 1, 4, 1].into();
+        let rmq = PowerRMQImpl::new(v);
+        assert_eq!(idx(1), rmq.rmq(idx(0)..idx(7)).idx());
+        assert_eq!(idx(3), rmq.rmq(idx(2)..idx(7)).idx());
+        assert_eq!(idx(4), rmq.rmq(idx(4)..idx(6)).idx());
+    }
+
+    // Simple LCG so we can get some larger test data without
+    // depending on a random number crate.
+    fn pseudo_random(n: usize, seed: u64, modulus: u32) -> Vec<u32> {
+        let mut state = seed;
+        (0..n)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                ((state >> 33) as u32) % modulus
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_block_signature() {
+        // Same shape, same signature
+        assert_eq!(
+            block_signature(&Smallest, &[3, 1, 2]),
+            block_signature(&Smallest, &[7, 2, 5])
+        );
+        // Different shapes
+        assert_ne!(
+            block_signature(&Smallest, &[1, 2, 3]),
+            block_signature(&Smallest, &[3, 2, 1])
+        );
+        // Ties count as "not smaller", so [1,1] is the same as [1,2]
+        assert_eq!(
+            block_signature(&Smallest, &[1, 1]),
+            block_signature(&Smallest, &[1, 2])
+        );
+        assert_ne!(
+            block_signature(&Smallest, &[1, 1]),
+            block_signature(&Smallest, &[2, 1])
+        );
+        // Different lengths
+        assert_ne!(
+            block_signature(&Smallest, &[1]),
+            block_signature(&Smallest, &[1, 2])
+        );
+        assert_ne!(
+            block_signature::<u32, _>(&Smallest, &[]),
+            block_signature(&Smallest, &[1])
+        );
+    }
+
+    #[test]
+    fn test_block_rmq() {
+        // Few distinct values, so we get lots of ties and shared tables
+        let v = pseudo_random(300, 42, 4);
+        check_against_naive(&BlockRMQImpl::new(v.clone().into()));
+        for bs in [1, 2, 3, 7, 8, 16, MAX_BLOCK_SIZE] {
+            let rmq = BlockRMQImpl::with_block_size(v.clone().into(), bs);
+            assert_eq!(bs, rmq.block_size());
+            check_against_naive(&rmq);
+        }
+        let v = pseudo_random(500, 7, 1000);
+        check_against_naive(&BlockRMQImpl::new(v.into()));
+    }
+
+    #[test]
+    fn test_block_rmq_tables_are_shared() {
+        // Every block is sorted, so they all have the same Cartesian tree
+        let v: Vec<u32> = (0..64).map(|i| i % 8).collect();
+        let rmq: BlockRMQImpl<Lcp> = BlockRMQImpl::with_block_size(v.into(), 8);
+        assert_eq!(1, rmq.tables.rows());
+        assert_eq!(idx(8), rmq.rmq(idx(1)..idx(12)).idx());
+    }
+
+    #[test]
+    fn test_rmq_orders() {
+        let v: IdxVec<Lcp> = vec![3, 1, 4, 1, 5, 9, 2, 6, 5, 9].into();
+        let all = idx(0)..idx(v.len());
+
+        let rmq = PowerRMQImpl::<Lcp, _>::from_slice(&v, Smallest);
+        assert_eq!(
+            (idx(1), 1),
+            (rmq.rmq(all.clone()).0, rmq.rmq(all.clone()).1)
+        );
+        let rmq = PowerRMQImpl::<Lcp, _>::from_slice(&v, Rightmost(Smallest));
+        assert_eq!(idx(3), rmq.rmq(all.clone()).idx());
+
+        // Range maximum
+        let rmq = BlockRMQImpl::<Lcp, _>::from_slice(&v, Largest);
+        assert_eq!(idx(5), rmq.rmq(all.clone()).idx());
+        assert_eq!(9, rmq.rmq(all.clone()).val());
+        let rmq = BlockRMQImpl::<Lcp, _>::from_slice(&v, Rightmost(Largest));
+        assert_eq!(idx(9), rmq.rmq(all.clone()).idx());
+        assert_eq!(idx(7), rmq.rmq(idx(6)..idx(9)).idx());
+
+        // Wrapping twice flips back to left-most
+        let rmq = NaiveRMQImpl::from_slice(&v, Rightmost(Rightmost(Smallest)));
+        assert_eq!(idx(1), rmq.rmq(all.clone()).idx());
+
+        // A user comparator: smallest value modulo 3, ties to the left
+        let mod3 = |a: &u32, b: &u32| (a % 3).cmp(&(b % 3));
+        let rmq = PowerRMQImpl::<Lcp, _>::from_slice(&v, mod3);
+        assert_eq!(idx(0), rmq.rmq(all.clone()).idx());
+        assert_eq!(idx(5), rmq.rmq(idx(1)..idx(7)).idx());
+        check_against_naive(&BlockRMQImpl::<Lcp, _>::with_order_and_block_size(
+            v.0.clone().into(),
+            mod3,
+            3,
+        ));
+    }
+
+    #[test]
+    fn test_rmq_non_ord_values() {
+        // With a comparator we are not limited to Ord values
+        #[derive(Clone, Copy, Debug)]
+        struct Scores();
+        impl SeqTrait for Scores {
+            type Type = f64;
+        }
+        impl CanIndex<Scores> for I {}
+
+        let v: IdxVec<Scores> = vec![0.5, -1.25, 3.0, -1.25, 2.0].into();
+        let rmq = BlockRMQImpl::with_order(v, |a: &f64, b: &f64| a.total_cmp(b));
+        let Point(i, x) = rmq.rmq(idx(0)..idx(5));
+        assert_eq!((idx(1), -1.25), (i, x));
+        let rmq = PowerRMQImpl::<Scores, _>::from_slice(
+            rmq.values(),
+            Rightmost(|a: &f64, b: &f64| b.total_cmp(a)),
+        );
+        assert_eq!(idx(2), rmq.rmq(idx(0)..idx(5)).idx());
+    }
+
+    fn check_batch<R>(rmq: &R)
+    where
+        R: RangeMinQuery<Lcp>,
+    {
+        // All ranges, in an order that isn't sorted by start
+        let n = rmq.len();
+        let queries: Vec<_> = (1..n + 1)
+            .flat_map(|j| (0..j).rev().map(move |i| idx(i)..idx(j)))
+            .collect();
+        let res = rmq.rmq_many(&queries);
+        assert_eq!(queries.len(), res.len());
+        for (r, Point(k, v)) in queries.iter().zip(res) {
+            let Point(k2, v2) = rmq.rmq(r.clone());
+            assert_eq!((k2, v2), (k, v));
+        }
+    }
+
+    #[test]
+    fn test_rmq_many() {
+        // Long enough for short scans, lanes with tails, and table lookups
+        let v: Vec<u32> = (0..150).map(|i| (i * 7919 + 13) % 23).collect();
+        let v: IdxVec<Lcp> = v.into();
+        check_batch(&PowerRMQImpl::<Lcp, _>::from_slice(&v, Smallest));
+        check_batch(&BlockRMQImpl::<Lcp, _>::from_slice(&v, Largest));
+        check_batch(&PowerRMQImpl::<Lcp, _>::from_slice(&v, Rightmost(Smallest)));
+        check_batch(&NaiveRMQImpl::from_slice(&v, Rightmost(Largest)));
+
+        // The lane scan agrees with the plain scan
+        let x = v.as_slice();
+        for i in 0..x.len() {
+            for j in i + 1..x.len() + 1 {
+                assert_eq!(scan(&Smallest, x, i, j), lane_scan(&Smallest, x, i, j));
+                let order = Rightmost(Largest);
+                assert_eq!(scan(&order, x, i, j), lane_scan(&order, x, i, j));
+            }
+        }
+
+        let empty: IdxVec<Lcp> = vec![].into();
+        let rmq = BlockRMQImpl::new(empty);
+        assert!(rmq.rmq_many::<Val<I>>(&[]).is_empty());
+    }
+
+    fn check_simd_scan<T: SimdScan + std::fmt::Debug>(x: &[T]) {
+        for i in 0..x.len() {
+            for j in i + 1..usize::min(x.len(), i + 100) + 1 {
+                assert_eq!(scan(&Smallest, x, i, j), Smallest.short_scan(x, i, j));
+                assert_eq!(scan(&Largest, x, i, j), Largest.short_scan(x, i, j));
+            }
+        }
+    }
+
+    #[test]
+    fn test_simd_scan() {
+        // Few distinct values, so there are ties within and across lanes
+        let v: Vec<u64> = (0..200).map(|i| (i * 7919 + 13) % 11).collect();
+        check_simd_scan(&v.iter().map(|&x| x as u8).collect::<Vec<_>>());
+        check_simd_scan(&v.iter().map(|&x| x as i16 - 5).collect::<Vec<_>>());
+        check_simd_scan(&v.iter().map(|&x| x as u32).collect::<Vec<_>>());
+        check_simd_scan(&v.iter().map(|&x| x as i64 - 5).collect::<Vec<_>>());
+        check_simd_scan(&v.iter().map(|&x| x as usize).collect::<Vec<_>>());
+        // Long ranges, where ties are far apart
+        let x: Vec<u8> = (0..10_000).map(|i| (i * 31 % 251) as u8).collect();
+        assert_eq!(
+            scan(&Smallest, &x, 0, x.len()),
+            Smallest.short_scan(&x, 0, x.len())
+        );
+        assert_eq!(
+            scan(&Largest, &x, 3, x.len()),
+            Largest.short_scan(&x, 3, x.len())
+        );
+    }
 }
-*/
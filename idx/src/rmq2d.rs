@@ -0,0 +1,251 @@
+use crate::rmq::{adjusted_index, first, Order, Point, Pow, Smallest};
+use crate::*;
+use std::ops::Range;
+
+// Table of positions, as row * cols + col, for one pair of powers.
+type Level<P> = Table<usize, usize, P>;
+
+// Sparse table size for n rows or columns
+fn powers(n: usize) -> usize {
+    match n {
+        0 => 0,
+        _ => rmq::log_table_size(n).0,
+    }
+}
+
+/// Two-dimensional RMQ over a matrix with rows indexed by R and columns
+/// indexed by C. It is the sparse table from PowerRMQImpl in both
+/// dimensions: for each pair of powers (2^a, 2^b) we tabulate the first
+/// value in each rectangle [i,i+2^a) x [j,j+2^b), and any rectangle is
+/// covered by four (overlapping) such rectangles. That gives us
+/// O(nm log n log m) preprocessing and O(1) queries.
+///
+/// Ties are broken in row-major order, so with the default order we get
+/// the smallest value in the top-most row that has one, and the left-most
+/// in that row.
+///
+/// The tables store positions as P, so for matrices with fewer than 2^32
+/// cells we can halve their size with P = u32, as with the cells of
+/// PowerRMQImpl.
+pub struct PowerRMQ2D<R, C, T, O = Smallest, P = usize> {
+    values: Table<R, C, T>,
+    order: O,
+    // levels[(Pow(a), Pow(b))][(i, j)] is the first position in
+    // [i,i+2^a) x [j,j+2^b).
+    levels: Table<Pow, Pow, Level<P>>,
+}
+
+impl<R, C, T> PowerRMQ2D<R, C, T>
+where
+    R: IndexType + Copy,
+    C: IndexType + Copy,
+    T: Ord + Copy,
+{
+    pub fn new(values: Table<R, C, T>) -> PowerRMQ2D<R, C, T> {
+        Self::with_order(values, Smallest)
+    }
+}
+
+impl<R, C, T, O> PowerRMQ2D<R, C, T, O>
+where
+    R: IndexType + Copy,
+    C: IndexType + Copy,
+    T: Copy,
+    O: Order<T>,
+{
+    pub fn with_order(values: Table<R, C, T>, order: O) -> PowerRMQ2D<R, C, T, O> {
+        Self::build(values, order)
+    }
+}
+
+impl<R, C, T, O, P> PowerRMQ2D<R, C, T, O, P>
+where
+    R: IndexType + Copy,
+    C: IndexType + Copy,
+    T: Copy,
+    O: Order<T>,
+    P: IndexType + Copy + Default,
+{
+    /// Build the structure with positions stored as P.
+    pub fn build(values: Table<R, C, T>, order: O) -> PowerRMQ2D<R, C, T, O, P> {
+        let (n, m) = (values.rows(), values.cols());
+        let (logn, logm) = (powers(n), powers(m));
+        let x = values.as_slice();
+        let pick = |p1: P, p2: P| {
+            let (p1, p2) = (p1.index(), p2.index());
+            P::from_index(first(&order, Point(p1, x[p1]), Point(p2, x[p2])).idx())
+        };
+
+        // Levels in row-major order, so the ones we build from are
+        // already there: (a,b-1) right before (a,b) and (a-1,b) a row up.
+        let mut levels: Vec<Level<P>> = Vec::with_capacity(logn * logm);
+        for a in 0..logn {
+            for b in 0..logm {
+                let mut level = Level::new(n, m);
+                if a == 0 && b == 0 {
+                    for p in 0..n * m {
+                        level[(p / m, p % m)] = P::from_index(p);
+                    }
+                } else if a == 0 {
+                    // [i,i+1) x [j,j+2^b) = [i,i+1) x [j,j+2^{b-1}) [j+2^{b-1},j+2^b)
+                    let (prev, half) = (&levels[b - 1], Pow(b - 1).value());
+                    for i in 0..n {
+                        for j in 0..=(m - Pow(b).value()) {
+                            level[(i, j)] = pick(prev[(i, j)], prev[(i, j + half)]);
+                        }
+                    }
+                } else {
+                    // [i,i+2^a) x [j,j+2^b) = the same over [i,i+2^{a-1}) and [i+2^{a-1},i+2^a)
+                    let (prev, half) = (&levels[(a - 1) * logm + b], Pow(a - 1).value());
+                    for i in 0..=(n - Pow(a).value()) {
+                        for j in 0..=(m - Pow(b).value()) {
+                            level[(i, j)] = pick(prev[(i, j)], prev[(i + half, j)]);
+                        }
+                    }
+                }
+                levels.push(level);
+            }
+        }
+
+        let levels = Table::from_vec(logn, logm, levels);
+        PowerRMQ2D {
+            values,
+            order,
+            levels,
+        }
+    }
+
+    #[inline]
+    pub fn rows(&self) -> usize {
+        self.values.rows()
+    }
+
+    #[inline]
+    pub fn cols(&self) -> usize {
+        self.values.cols()
+    }
+
+    /// The matrix the structure was built from.
+    #[inline]
+    pub fn values(&self) -> &Table<R, C, T> {
+        &self.values
+    }
+
+    #[inline]
+    pub fn order(&self) -> &O {
+        &self.order
+    }
+
+    /// Get the first point in the rectangle rows x cols, with its
+    /// position as a (row, column) pair. The rectangle must be non-empty.
+    pub fn rmq(&self, rows: Range<R>, cols: Range<C>) -> Point<(R, C), T> {
+        let (i, ii) = (rows.start.index(), rows.end.index());
+        let (j, jj) = (cols.start.index(), cols.end.index());
+        assert!(
+            i < ii && ii <= self.rows() && j < jj && jj <= self.cols(),
+            "rmq needs a non-empty rectangle"
+        );
+
+        let ((a, i2), (b, j2)) = (adjusted_index(i, ii), adjusted_index(j, jj));
+        let level = &self.levels[(a, b)];
+        let x = self.values.as_slice();
+        let point = |r: usize, c: usize| {
+            let p = level[(r, c)].index();
+            Point(p, x[p])
+        };
+        let top = first(&self.order, point(i, j), point(i, j2));
+        let bottom = first(&self.order, point(i2, j), point(i2, j2));
+        let Point(p, v) = first(&self.order, top, bottom);
+
+        let m = self.cols();
+        Point((R::from_index(p / m), C::from_index(p % m)), v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rmq::{right_wins, Largest, Rightmost};
+
+    #[rustfmt::skip]
+    mod types {
+        use crate::*;
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Row {}
+        impl TypeTrait for Row { type Type = u16; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Col {}
+        impl TypeTrait for Col { type Type = u32; }
+    }
+    use types::*;
+
+    type Scores = Table<Val<Row>, Val<Col>, i32>;
+
+    fn row(i: usize) -> Val<Row> {
+        i.into()
+    }
+    fn col(j: usize) -> Val<Col> {
+        j.into()
+    }
+
+    fn check_rmq<O: Order<i32> + Clone>(n: usize, m: usize, order: O) {
+        let cells: Vec<i32> = (0..n * m)
+            .map(|p| ((p * 7919 + 3) % 17) as i32 - 8)
+            .collect();
+        let scores = Scores::from_vec(n, m, cells);
+        let rmq = PowerRMQ2D::with_order(scores, order.clone());
+        let x = rmq.values();
+
+        for (i, ii) in (0..n).flat_map(|i| (i + 1..=n).map(move |ii| (i, ii))) {
+            for (j, jj) in (0..m).flat_map(|j| (j + 1..=m).map(move |jj| (j, jj))) {
+                // Naive scan in row-major order
+                let mut best = (i, j);
+                for r in i..ii {
+                    for c in j..jj {
+                        let cur = x[(row(best.0), col(best.1))];
+                        if right_wins(&order, &cur, &x[(row(r), col(c))]) {
+                            best = (r, c);
+                        }
+                    }
+                }
+                let Point((r, c), v) = rmq.rmq(row(i)..row(ii), col(j)..col(jj));
+                assert_eq!((row(best.0), col(best.1)), (r, c));
+                assert_eq!(x[(r, c)], v);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rmq2d() {
+        check_rmq(5, 7, Smallest);
+        check_rmq(8, 4, Largest);
+        check_rmq(6, 6, Rightmost(Smallest));
+        check_rmq(1, 9, Smallest);
+        check_rmq(9, 1, Rightmost(Largest));
+
+        // Narrow positions give the same answers
+        let cells: Vec<i32> = (0..35).map(|p| p * 13 % 11).collect();
+        let wide = PowerRMQ2D::new(Scores::from_vec(5, 7, cells.clone()));
+        let narrow: PowerRMQ2D<_, _, _, _, u16> =
+            PowerRMQ2D::build(Scores::from_vec(5, 7, cells), Smallest);
+        for (i, j) in [(0, 0), (1, 3), (4, 6)] {
+            let (rows, cols) = (row(i)..row(5), col(j)..col(7));
+            let (Point(p1, v1), Point(p2, v2)) =
+                (wide.rmq(rows.clone(), cols.clone()), narrow.rmq(rows, cols));
+            assert_eq!((p1, v1), (p2, v2));
+        }
+
+        let scores = Scores::from_vec(2, 3, vec![4, 1, 5, 1, 6, 1]);
+        let rmq = PowerRMQ2D::new(scores);
+        assert_eq!((2, 3), (rmq.rows(), rmq.cols()));
+        // Ties go to the top-most row, then the left-most column
+        let Point(pos, v) = rmq.rmq(row(0)..row(2), col(0)..col(3));
+        assert_eq!(((row(0), col(1)), 1), (pos, v));
+        let Point(pos, _) = rmq.rmq(row(1)..row(2), col(0)..col(3));
+        assert_eq!((row(1), col(0)), pos);
+        let Point(pos, _) = rmq.rmq(row(0)..row(2), col(2)..col(3));
+        assert_eq!((row(1), col(2)), pos);
+    }
+}
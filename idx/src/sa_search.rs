@@ -0,0 +1,243 @@
+use crate::*;
+use std::cmp::Ordering;
+use std::ops::Range;
+
+// Compare pattern against a suffix, knowing they share the first h
+// characters. A suffix that starts with the pattern is Equal. Returns the
+// order of the suffix relative to the pattern and the length of their
+// common prefix.
+fn compare<T: Ord>(suffix: &[T], p: &[T], mut h: usize) -> (Ordering, usize) {
+    while h < p.len() && h < suffix.len() && suffix[h] == p[h] {
+        h += 1;
+    }
+    let ord = if h == p.len() {
+        Ordering::Equal
+    } else if h == suffix.len() {
+        // The suffix is a proper prefix of the pattern
+        Ordering::Less
+    } else {
+        suffix[h].cmp(&p[h])
+    };
+    (ord, h)
+}
+
+// The first of the n sorted suffixes where go_right is false, with the
+// mlr heuristic: lcp_lo and lcp_hi are the lengths of the common prefixes
+// of the pattern and the suffixes at the boundaries, and every suffix
+// between the boundaries shares at least the smaller of those with the
+// pattern, so we can skip that many characters in each comparison.
+fn partition_point<'a, T: Ord + 'a>(
+    n: usize,
+    suffix: &impl Fn(usize) -> &'a [T],
+    p: &[T],
+    go_right: impl Fn(Ordering) -> bool,
+) -> usize {
+    // Boundaries are exclusive on the left, so lo starts before the array.
+    let (mut lo, mut hi) = (0, n + 1);
+    let (mut lcp_lo, mut lcp_hi) = (0, 0);
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        let h = usize::min(lcp_lo, lcp_hi);
+        let (ord, h) = compare(suffix(mid - 1), p, h);
+        if go_right(ord) {
+            (lo, lcp_lo) = (mid, h);
+        } else {
+            (hi, lcp_hi) = (mid, h);
+        }
+    }
+    hi - 1
+}
+
+/// The range of the suffixes that start with p among n sorted suffixes,
+/// where suffix(r) is the r'th, as raw positions. This is the search
+/// behind sa_search, for other kinds of suffix arrays.
+pub(crate) fn search_sorted<'a, T: Ord + 'a>(
+    n: usize,
+    suffix: impl Fn(usize) -> &'a [T],
+    p: &[T],
+) -> (usize, usize) {
+    let start = partition_point(n, &suffix, p, |ord| ord == Ordering::Less);
+    let end = partition_point(n, &suffix, p, |ord| ord != Ordering::Greater);
+    (start, end)
+}
+
+// The range of the suffixes that start with p, as raw positions.
+fn search<T, Idx>(x: &[T], sa: &[Idx], p: &[T]) -> (usize, usize)
+where
+    T: Ord,
+    Idx: IndexType + Copy,
+{
+    assert_eq!(
+        x.len(),
+        sa.len(),
+        "the suffix array must have the text's length"
+    );
+    search_sorted(sa.len(), |r| &x[sa[r].index()..], p)
+}
+
+/// The suffix array positions of the suffixes of text that start with
+/// pattern, found with two binary searches over the suffix array. Each
+/// search keeps track of how much of the pattern the suffixes at its two
+/// boundaries match (the mlr heuristic of Manber and Myers), so the
+/// comparisons don't start over from the first character. That is
+/// O(m log n) in the worst case but close to O(m + log n) in practice.
+/// If the pattern doesn't occur, the range is empty and starts where the
+/// pattern would be inserted.
+pub fn sa_search<_Text, _Sa, TextIdx, SaIdx>(
+    text: &IdxSlice<_Text>,
+    sa: &IdxSlice<_Sa>,
+    pattern: &IdxSlice<_Text>,
+) -> Range<SaIdx>
+where
+    _Text: SeqTrait,
+    _Text::Type: Ord,
+    _Sa: SeqTrait<Type = TextIdx>,
+    TextIdx: IndexType + CanIndex<_Text> + Copy,
+    SaIdx: IndexType + CanIndex<_Sa>,
+{
+    let (i, j) = search(text.as_slice(), sa.as_slice(), pattern.as_slice());
+    SaIdx::from_index(i)..SaIdx::from_index(j)
+}
+
+/// The number of occurrences of pattern in text.
+pub fn sa_count<_Text, _Sa, TextIdx>(
+    text: &IdxSlice<_Text>,
+    sa: &IdxSlice<_Sa>,
+    pattern: &IdxSlice<_Text>,
+) -> usize
+where
+    _Text: SeqTrait,
+    _Text::Type: Ord,
+    _Sa: SeqTrait<Type = TextIdx>,
+    TextIdx: IndexType + CanIndex<_Text> + Copy,
+{
+    let (i, j) = search(text.as_slice(), sa.as_slice(), pattern.as_slice());
+    j - i
+}
+
+/// The text positions where pattern occurs, in suffix array order.
+pub fn sa_locate<'a, _Text, _Sa, TextIdx>(
+    text: &IdxSlice<_Text>,
+    sa: &'a IdxSlice<_Sa>,
+    pattern: &IdxSlice<_Text>,
+) -> impl Iterator<Item = TextIdx> + 'a
+where
+    _Text: SeqTrait,
+    _Text::Type: Ord,
+    _Sa: SeqTrait<Type = TextIdx>,
+    TextIdx: IndexType + CanIndex<_Text> + Copy + 'a,
+{
+    let (i, j) = search(text.as_slice(), sa.as_slice(), pattern.as_slice());
+    sa.as_slice()[i..j].iter().copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::suffix_array::suffix_array;
+
+    #[rustfmt::skip]
+    mod types {
+        use crate::*;
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Text();
+        impl SeqTrait for Text { type Type = u8; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct TextIdx {}
+        impl TypeTrait for TextIdx { type Type = u32; }
+        impl CanIndex<Text> for TextIdx {}
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct SA();
+        impl SeqTrait for SA { type Type = Val<TextIdx>; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct SaIdx {}
+        impl TypeTrait for SaIdx { type Type = u32; }
+        impl CanIndex<SA> for SaIdx {}
+    }
+    use types::*;
+
+    fn check_search(x: &[u8], patterns: &[&[u8]]) {
+        let text: IdxVec<Text> = x.to_vec().into();
+        let sa: IdxVec<SA> = suffix_array(&text);
+        for &p in patterns {
+            let r: Range<Val<SaIdx>> = sa_search(&text, &sa, p.into());
+            // Everything before the range is smaller, everything after larger
+            for k in 0..x.len() {
+                let suffix = &x[sa[Val::<SaIdx>::from(k)].index()..];
+                let expected = if suffix.starts_with(p) {
+                    Ordering::Equal
+                } else {
+                    suffix.cmp(p)
+                };
+                let found = match k {
+                    k if k < r.start.index() => Ordering::Less,
+                    k if k < r.end.index() => Ordering::Equal,
+                    _ => Ordering::Greater,
+                };
+                assert_eq!(expected, found);
+            }
+
+            let mut located: Vec<usize> =
+                sa_locate(&text, &sa, p.into()).map(|i| i.index()).collect();
+            located.sort();
+            let expected: Vec<usize> = (0..x.len()).filter(|&i| x[i..].starts_with(p)).collect();
+            assert_eq!(expected, located);
+            assert_eq!(expected.len(), sa_count(&text, &sa, p.into()));
+        }
+    }
+
+    #[test]
+    fn test_sa_search() {
+        let pats: [&[u8]; 10] = [
+            b"",
+            b"i",
+            b"ss",
+            b"issi",
+            b"ssi",
+            b"pi",
+            b"x",
+            b"a",
+            b"mississippii",
+            b"ippi",
+        ];
+        check_search(b"mississippi", &pats);
+        check_search(b"", &pats);
+        check_search(b"aaaaaaaa", &[b"a", b"aaa", b"aaaaaaaaa", b"b", b""]);
+
+        let x: Vec<u8> = (0..3000u32)
+            .map(|i| b"acgt"[(i * i % 7 + i / 3) as usize % 4])
+            .collect();
+        check_search(
+            &x,
+            &[
+                b"a",
+                b"acg",
+                b"gattaca",
+                b"tt",
+                b"cgta",
+                &x[100..130],
+                &x[2990..],
+            ],
+        );
+    }
+
+    #[test]
+    fn test_typed_search() {
+        let text: IdxVec<Text> = b"banana".to_vec().into();
+        let sa: IdxVec<SA> = suffix_array(&text);
+        let na: &IdxSlice<Text> = b"na".as_slice().into();
+        let r: Range<Val<SaIdx>> = sa_search(&text, &sa, na);
+        assert_eq!(Val::<SaIdx>(4)..Val(6), r);
+        let pos: Vec<Val<TextIdx>> = sa_locate(&text, &sa, na).collect();
+        assert_eq!(vec![Val::<TextIdx>(4), Val(2)], pos);
+        assert_eq!(b'n', text[pos[1]]);
+        // Where "c" would go
+        let c: &IdxSlice<Text> = b"c".as_slice().into();
+        let r: Range<Val<SaIdx>> = sa_search(&text, &sa, c);
+        assert_eq!(Val::<SaIdx>(4)..Val(4), r);
+    }
+}
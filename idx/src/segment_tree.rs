@@ -0,0 +1,497 @@
+use crate::rmq::Point;
+use crate::sparse_table::{MaxOp, MinOp};
+use crate::*;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+/// Updates we can apply to all values in a range.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Update<T> {
+    /// Add a value to all the values in the range
+    Add(T),
+    /// Set all the values in the range to a value
+    Assign(T),
+}
+
+impl<T: NumType> Update<T> {
+    /// The update we get from first doing old and then self.
+    #[inline]
+    fn after(self, old: Update<T>) -> Update<T> {
+        use Update::*;
+        match (self, old) {
+            (Assign(x), _) => Assign(x),
+            (Add(d), Assign(x)) => Assign(x + d),
+            (Add(d), Add(e)) => Add(d + e),
+        }
+    }
+}
+
+/// A monoid we can fold ranges of values with in a segment tree. The
+/// tree holds a Node per segment; leaf() gives us the node for a single
+/// value and combine() the node for two adjacent segments (left first).
+/// It must be associative, with identity() as its neutral element.
+///
+/// For lazy range updates, apply() must give us the node we get if we
+/// apply an update to all values in a segment [start,start+len) that
+/// used to have the given node.
+pub trait Monoid<T> {
+    type Node: Clone;
+    fn identity(&self) -> Self::Node;
+    fn leaf(&self, i: usize, x: T) -> Self::Node;
+    fn combine(&self, a: &Self::Node, b: &Self::Node) -> Self::Node;
+    fn apply(&self, node: &Self::Node, upd: Update<T>, start: usize, len: usize) -> Self::Node;
+}
+
+/// Sum of values
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SumOp;
+
+impl<T: NumType> Monoid<T> for SumOp {
+    type Node = T;
+    #[inline]
+    fn identity(&self) -> T {
+        T::zero()
+    }
+    #[inline]
+    fn leaf(&self, _i: usize, x: T) -> T {
+        x
+    }
+    #[inline]
+    fn combine(&self, a: &T, b: &T) -> T {
+        *a + *b
+    }
+    #[inline]
+    fn apply(&self, node: &T, upd: Update<T>, _start: usize, len: usize) -> T {
+        match upd {
+            Update::Add(d) => *node + d * len.cast(),
+            Update::Assign(x) => x * len.cast(),
+        }
+    }
+}
+
+impl<T: NumType + num::Bounded> Monoid<T> for MinOp {
+    type Node = T;
+    #[inline]
+    fn identity(&self) -> T {
+        T::max_value()
+    }
+    #[inline]
+    fn leaf(&self, _i: usize, x: T) -> T {
+        x
+    }
+    #[inline]
+    fn combine(&self, a: &T, b: &T) -> T {
+        if b < a {
+            *b
+        } else {
+            *a
+        }
+    }
+    #[inline]
+    fn apply(&self, node: &T, upd: Update<T>, _start: usize, _len: usize) -> T {
+        match upd {
+            Update::Add(d) => *node + d,
+            Update::Assign(x) => x,
+        }
+    }
+}
+
+impl<T: NumType + num::Bounded> Monoid<T> for MaxOp {
+    type Node = T;
+    #[inline]
+    fn identity(&self) -> T {
+        T::min_value()
+    }
+    #[inline]
+    fn leaf(&self, _i: usize, x: T) -> T {
+        x
+    }
+    #[inline]
+    fn combine(&self, a: &T, b: &T) -> T {
+        if b > a {
+            *b
+        } else {
+            *a
+        }
+    }
+    #[inline]
+    fn apply(&self, node: &T, upd: Update<T>, _start: usize, _len: usize) -> T {
+        match upd {
+            Update::Add(d) => *node + d,
+            Update::Assign(x) => x,
+        }
+    }
+}
+
+/// The (left-most) position of the smallest value, as a point with
+/// index type Idx. Folding an empty range gives None.
+pub struct ArgMinOp<Idx>(PhantomData<Idx>);
+
+/// The (left-most) position of the largest value, as a point with
+/// index type Idx. Folding an empty range gives None.
+pub struct ArgMaxOp<Idx>(PhantomData<Idx>);
+
+impl<Idx> Default for ArgMinOp<Idx> {
+    fn default() -> Self {
+        ArgMinOp(PhantomData)
+    }
+}
+
+impl<Idx> Default for ArgMaxOp<Idx> {
+    fn default() -> Self {
+        ArgMaxOp(PhantomData)
+    }
+}
+
+// Shared implementation of ArgMinOp and ArgMaxOp; they only differ in
+// when the right point wins.
+macro_rules! arg_monoid {
+    ($name:ident, $right_wins:tt) => {
+        impl<Idx, T> Monoid<T> for $name<Idx>
+        where
+            Idx: IndexType + Copy,
+            T: NumType,
+        {
+            type Node = Option<Point<Idx, T>>;
+            #[inline]
+            fn identity(&self) -> Self::Node {
+                None
+            }
+            #[inline]
+            fn leaf(&self, i: usize, x: T) -> Self::Node {
+                Some(Point(Idx::from_index(i), x))
+            }
+            #[inline]
+            fn combine(&self, a: &Self::Node, b: &Self::Node) -> Self::Node {
+                match (a, b) {
+                    (Some(p), Some(q)) if q.1 $right_wins p.1 => *b,
+                    (None, _) => *b,
+                    _ => *a,
+                }
+            }
+            #[inline]
+            fn apply(&self, node: &Self::Node, upd: Update<T>, start: usize, _len: usize) -> Self::Node {
+                match upd {
+                    Update::Add(d) => node.map(|Point(i, x)| Point(i, x + d)),
+                    // All values are the same now, so the left-most wins.
+                    Update::Assign(x) => Some(Point(Idx::from_index(start), x)),
+                }
+            }
+        }
+    };
+}
+arg_monoid!(ArgMinOp, <);
+arg_monoid!(ArgMaxOp, >);
+
+/// Segment tree over a sequence of values, parameterised by a monoid. It
+/// supports point assignment, lazy range updates (add or assign), and
+/// folding a range with the monoid, all in O(log n). Positions are given
+/// with any index type that can index _Tag.
+pub struct SegmentTree<_Tag: SeqTrait, M: Monoid<_Tag::Type>> {
+    n: usize,
+    monoid: M,
+    // Nodes in heap order; the root is at 1 and the children of v at 2v
+    // and 2v+1. Node v covers a range that we compute on the way down.
+    nodes: Vec<M::Node>,
+    // Updates we haven't pushed down to the children of v yet.
+    lazy: Vec<Option<Update<_Tag::Type>>>,
+    _tag: PhantomData<_Tag>,
+}
+
+impl<_Tag, M> SegmentTree<_Tag, M>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: NumType,
+    M: Monoid<_Tag::Type>,
+{
+    pub fn new(values: &IdxSlice<_Tag>, monoid: M) -> SegmentTree<_Tag, M> {
+        let n = values.len();
+        let size = 4 * n.max(1);
+        let mut tree = SegmentTree {
+            n,
+            nodes: vec![monoid.identity(); size],
+            lazy: vec![None; size],
+            monoid,
+            _tag: PhantomData,
+        };
+        if n > 0 {
+            tree.build(1, 0, n, values.as_slice());
+        }
+        tree
+    }
+
+    fn build(&mut self, v: usize, lo: usize, hi: usize, x: &[_Tag::Type]) {
+        if hi - lo == 1 {
+            self.nodes[v] = self.monoid.leaf(lo, x[lo]);
+            return;
+        }
+        let mid = (lo + hi) / 2;
+        self.build(2 * v, lo, mid, x);
+        self.build(2 * v + 1, mid, hi, x);
+        self.nodes[v] = self
+            .monoid
+            .combine(&self.nodes[2 * v], &self.nodes[2 * v + 1]);
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// The monoid the tree folds with.
+    #[inline]
+    pub fn monoid(&self) -> &M {
+        &self.monoid
+    }
+
+    // Apply upd to node v, covering [lo,hi), and remember to pass it
+    // on to the children later.
+    fn apply(&mut self, v: usize, lo: usize, hi: usize, upd: Update<_Tag::Type>) {
+        self.nodes[v] = self.monoid.apply(&self.nodes[v], upd, lo, hi - lo);
+        if hi - lo > 1 {
+            self.lazy[v] = Some(match self.lazy[v] {
+                Some(old) => upd.after(old),
+                None => upd,
+            });
+        }
+    }
+
+    // Push pending updates in node v, covering [lo,hi), to its children.
+    fn push(&mut self, v: usize, lo: usize, hi: usize) {
+        if let Some(upd) = self.lazy[v].take() {
+            let mid = (lo + hi) / 2;
+            self.apply(2 * v, lo, mid, upd);
+            self.apply(2 * v + 1, mid, hi, upd);
+        }
+    }
+
+    fn update_rec(
+        &mut self,
+        v: usize,
+        lo: usize,
+        hi: usize,
+        (i, j): (usize, usize),
+        upd: Update<_Tag::Type>,
+    ) {
+        if j <= lo || hi <= i {
+            return;
+        }
+        if i <= lo && hi <= j {
+            self.apply(v, lo, hi, upd);
+            return;
+        }
+        self.push(v, lo, hi);
+        let mid = (lo + hi) / 2;
+        self.update_rec(2 * v, lo, mid, (i, j), upd);
+        self.update_rec(2 * v + 1, mid, hi, (i, j), upd);
+        self.nodes[v] = self
+            .monoid
+            .combine(&self.nodes[2 * v], &self.nodes[2 * v + 1]);
+    }
+
+    fn fold_rec(&mut self, v: usize, lo: usize, hi: usize, (i, j): (usize, usize)) -> M::Node {
+        if j <= lo || hi <= i {
+            return self.monoid.identity();
+        }
+        if i <= lo && hi <= j {
+            return self.nodes[v].clone();
+        }
+        self.push(v, lo, hi);
+        let mid = (lo + hi) / 2;
+        let left = self.fold_rec(2 * v, lo, mid, (i, j));
+        let right = self.fold_rec(2 * v + 1, mid, hi, (i, j));
+        self.monoid.combine(&left, &right)
+    }
+
+    // Checks a range and gets the raw positions out of it.
+    #[inline]
+    fn checked_range<Idx: IndexType + Copy>(&self, r: &Range<Idx>) -> (usize, usize) {
+        let (i, j) = (r.start.index(), r.end.index());
+        assert!(i <= j && j <= self.n, "range out of bounds");
+        (i, j)
+    }
+
+    /// Apply upd to all values in the range r.
+    pub fn update<Idx>(&mut self, r: Range<Idx>, upd: Update<_Tag::Type>)
+    where
+        Idx: IndexType + CanIndex<_Tag> + Copy,
+    {
+        let (i, j) = self.checked_range(&r);
+        if i < j {
+            self.update_rec(1, 0, self.n, (i, j), upd);
+        }
+    }
+
+    /// Add d to all values in the range r.
+    pub fn add<Idx>(&mut self, r: Range<Idx>, d: _Tag::Type)
+    where
+        Idx: IndexType + CanIndex<_Tag> + Copy,
+    {
+        self.update(r, Update::Add(d));
+    }
+
+    /// Set all values in the range r to x.
+    pub fn assign<Idx>(&mut self, r: Range<Idx>, x: _Tag::Type)
+    where
+        Idx: IndexType + CanIndex<_Tag> + Copy,
+    {
+        self.update(r, Update::Assign(x));
+    }
+
+    /// Set the value at position i to x.
+    pub fn set<Idx>(&mut self, i: Idx, x: _Tag::Type)
+    where
+        Idx: IndexType + CanIndex<_Tag> + Copy,
+    {
+        let i = i.index();
+        assert!(i < self.n, "index out of bounds");
+        self.update_rec(1, 0, self.n, (i, i + 1), Update::Assign(x));
+    }
+
+    /// Fold the values in the range r with the monoid. An empty range
+    /// gives the identity. This takes &mut self because we push pending
+    /// updates down the tree as we go.
+    pub fn fold<Idx>(&mut self, r: Range<Idx>) -> M::Node
+    where
+        Idx: IndexType + CanIndex<_Tag> + Copy,
+    {
+        let (i, j) = self.checked_range(&r);
+        if i == j {
+            return self.monoid.identity();
+        }
+        self.fold_rec(1, 0, self.n, (i, j))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rustfmt::skip]
+    mod types {
+        use crate::*;
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Xs();
+        impl SeqTrait for Xs { type Type = i64; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct I {}
+        impl TypeTrait for I { type Type = u32; }
+        impl CanIndex<Xs> for I {}
+    }
+    use types::*;
+
+    fn idx(i: usize) -> Val<I> {
+        i.into()
+    }
+
+    // Naive argmin/argmax, left-most on ties
+    fn arg<F: Fn(i64, i64) -> bool>(
+        v: &[i64],
+        i: usize,
+        j: usize,
+        wins: F,
+    ) -> Option<(usize, i64)> {
+        let mut best: Option<(usize, i64)> = None;
+        for (k, &x) in v.iter().enumerate().take(j).skip(i) {
+            match best {
+                Some((_, y)) if !wins(x, y) => {}
+                _ => best = Some((k, x)),
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn test_segment_tree() {
+        let mut v: Vec<i64> = vec![5, -3, 8, 0, 2, 2, -7, 4, 1, 9, -1];
+        let n = v.len();
+        let xs: IdxVec<Xs> = v.clone().into();
+        let mut sum = SegmentTree::new(&xs, SumOp);
+        let mut mins = SegmentTree::new(&xs, MinOp);
+        let mut maxs = SegmentTree::new(&xs, MaxOp);
+        let mut argmin = SegmentTree::new(&xs, ArgMinOp::<Val<I>>::default());
+        let mut argmax = SegmentTree::new(&xs, ArgMaxOp::<Val<I>>::default());
+
+        let check = |v: &[i64],
+                     sum: &mut SegmentTree<Xs, SumOp>,
+                     mins: &mut SegmentTree<Xs, MinOp>,
+                     maxs: &mut SegmentTree<Xs, MaxOp>,
+                     argmin: &mut SegmentTree<Xs, ArgMinOp<Val<I>>>,
+                     argmax: &mut SegmentTree<Xs, ArgMaxOp<Val<I>>>| {
+            for i in 0..n {
+                for j in i..n + 1 {
+                    let r = idx(i)..idx(j);
+                    assert_eq!(v[i..j].iter().sum::<i64>(), sum.fold(r.clone()));
+                    let min = v[i..j].iter().copied().min().unwrap_or(i64::MAX);
+                    assert_eq!(min, mins.fold(r.clone()));
+                    let max = v[i..j].iter().copied().max().unwrap_or(i64::MIN);
+                    assert_eq!(max, maxs.fold(r.clone()));
+                    let p = argmin.fold(r.clone()).map(|Point(k, x)| (k.0 as usize, x));
+                    assert_eq!(arg(v, i, j, |x, y| x < y), p);
+                    let p = argmax.fold(r.clone()).map(|Point(k, x)| (k.0 as usize, x));
+                    assert_eq!(arg(v, i, j, |x, y| x > y), p);
+                }
+            }
+        };
+        check(&v, &mut sum, &mut mins, &mut maxs, &mut argmin, &mut argmax);
+
+        // A mix of overlapping updates, so we exercise the lazy composition
+        let updates = [
+            (2, 9, Update::Add(3)),
+            (0, 4, Update::Assign(1)),
+            (3, 11, Update::Add(-2)),
+            (5, 6, Update::Assign(-10)),
+            (1, 8, Update::Add(5)),
+            (6, 11, Update::Assign(0)),
+            (4, 10, Update::Add(1)),
+        ];
+        for (i, j, upd) in updates {
+            for x in &mut v[i..j] {
+                *x = match upd {
+                    Update::Add(d) => *x + d,
+                    Update::Assign(y) => y,
+                }
+            }
+            sum.update(idx(i)..idx(j), upd);
+            mins.update(idx(i)..idx(j), upd);
+            maxs.update(idx(i)..idx(j), upd);
+            argmin.update(idx(i)..idx(j), upd);
+            argmax.update(idx(i)..idx(j), upd);
+            check(&v, &mut sum, &mut mins, &mut maxs, &mut argmin, &mut argmax);
+        }
+
+        // Point assignments
+        for (i, x) in [(0, 7), (10, -20), (5, 3)] {
+            v[i] = x;
+            sum.set(idx(i), x);
+            mins.set(idx(i), x);
+            maxs.set(idx(i), x);
+            argmin.set(idx(i), x);
+            argmax.set(idx(i), x);
+            check(&v, &mut sum, &mut mins, &mut maxs, &mut argmin, &mut argmax);
+        }
+    }
+
+    #[test]
+    fn test_add_and_assign() {
+        let xs: IdxVec<Xs> = vec![1, 2, 3, 4].into();
+        let mut tree = SegmentTree::new(&xs, SumOp);
+        tree.add(idx(1)..idx(3), 10);
+        assert_eq!(30, tree.fold(idx(0)..idx(4)));
+        tree.assign(idx(0)..idx(2), 0);
+        assert_eq!(17, tree.fold(idx(0)..idx(4)));
+        assert_eq!(0, tree.fold(idx(2)..idx(2)));
+
+        let empty: IdxVec<Xs> = vec![].into();
+        let mut tree = SegmentTree::new(&empty, ArgMinOp::<Val<I>>::default());
+        assert!(tree.is_empty());
+        assert!(tree.fold(idx(0)..idx(0)).is_none());
+    }
+}
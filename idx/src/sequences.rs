@@ -32,6 +32,24 @@ where
     }
 }
 
+impl<_Tag: SeqTrait> IdxSlice<_Tag> {
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// Get the underlying slice. This throws away the type information
+    /// for indexing, so only use it where you have to work with raw
+    /// positions.
+    #[inline]
+    pub fn as_slice(&self) -> &[_Tag::Type] {
+        &self.0
+    }
+}
+
 impl<Idx, _Tag> Index<Idx> for IdxSlice<_Tag>
 where
     _Tag: SeqTrait,
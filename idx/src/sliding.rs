@@ -0,0 +1,204 @@
+use crate::rmq::{right_wins, Largest, Order, Smallest};
+use crate::*;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+
+/// Iterator over the first value, according to an order, in all windows
+/// x[i..i+w) of a sequence. It yields (i, j, x[j]) for each window start
+/// i, where j is the position of the first value in the window, with ties
+/// broken as in the RMQ structures.
+///
+/// We keep a deque of the positions in the window that can still be the
+/// first in some later window, i.e., those that nothing to their right
+/// wins over. The values along the deque are then sorted by the order,
+/// so the front of the deque is the first in the window. Each position
+/// goes in and out of the deque once, so the whole iteration is O(n).
+pub struct SlidingWindow<'a, _Tag: SeqTrait, Idx, O> {
+    x: &'a [_Tag::Type],
+    order: O,
+    w: usize,
+    start: usize,
+    // Positions we have pushed so far are [0,pushed)
+    pushed: usize,
+    deque: VecDeque<usize>,
+    _idx: PhantomData<Idx>,
+}
+
+impl<'a, _Tag, Idx, O> SlidingWindow<'a, _Tag, Idx, O>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Copy,
+    Idx: IndexType + CanIndex<_Tag> + Copy,
+    O: Order<_Tag::Type>,
+{
+    /// Windows of length w over x. The window length can be any index
+    /// type, so it can be the offset type that goes with Idx, but it must
+    /// be positive. If x is shorter than w, there are no windows.
+    pub fn new<W: IndexType>(x: &'a IdxSlice<_Tag>, w: W, order: O) -> Self {
+        let w = w.index();
+        assert!(w > 0, "windows must have a positive length");
+        SlidingWindow {
+            x: x.as_slice(),
+            order,
+            w,
+            start: 0,
+            pushed: 0,
+            deque: VecDeque::with_capacity(w.min(x.len())),
+            _idx: PhantomData,
+        }
+    }
+}
+
+impl<_Tag, Idx, O> Iterator for SlidingWindow<'_, _Tag, Idx, O>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Copy,
+    Idx: IndexType + CanIndex<_Tag> + Copy,
+    O: Order<_Tag::Type>,
+{
+    type Item = (Idx, Idx, _Tag::Type);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let end = self.start + self.w;
+        if end > self.x.len() {
+            return None;
+        }
+        let x = self.x;
+        for i in self.pushed..end {
+            while matches!(self.deque.back(), Some(&j) if right_wins(&self.order, &x[j], &x[i])) {
+                self.deque.pop_back();
+            }
+            self.deque.push_back(i);
+        }
+        self.pushed = end;
+        while matches!(self.deque.front(), Some(&j) if j < self.start) {
+            self.deque.pop_front();
+        }
+
+        let m = self.deque[0];
+        let start = self.start;
+        self.start += 1;
+        Some((Idx::from_index(start), Idx::from_index(m), x[m]))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = (self.x.len() + 1).saturating_sub(self.start + self.w);
+        (n, Some(n))
+    }
+}
+
+impl<_Tag, Idx, O> ExactSizeIterator for SlidingWindow<'_, _Tag, Idx, O>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Copy,
+    Idx: IndexType + CanIndex<_Tag> + Copy,
+    O: Order<_Tag::Type>,
+{
+}
+
+/// The left-most minimum in all windows of length w over x.
+pub fn sliding_min<_Tag, Idx, W>(x: &IdxSlice<_Tag>, w: W) -> SlidingWindow<'_, _Tag, Idx, Smallest>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Ord + Copy,
+    Idx: IndexType + CanIndex<_Tag> + Copy,
+    W: IndexType,
+{
+    SlidingWindow::new(x, w, Smallest)
+}
+
+/// The left-most maximum in all windows of length w over x.
+pub fn sliding_max<_Tag, Idx, W>(x: &IdxSlice<_Tag>, w: W) -> SlidingWindow<'_, _Tag, Idx, Largest>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Ord + Copy,
+    Idx: IndexType + CanIndex<_Tag> + Copy,
+    W: IndexType,
+{
+    SlidingWindow::new(x, w, Largest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rmq::{NaiveRMQImpl, RangeMinQuery, Rightmost};
+
+    #[rustfmt::skip]
+    mod types {
+        use crate::*;
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Xs();
+        impl SeqTrait for Xs { type Type = u32; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct I {}
+        impl TypeTrait for I { type Type = u32; }
+        impl CanIndex<Xs> for I {}
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Len {}
+        impl TypeTrait for Len { type Type = u16; }
+    }
+    use types::*;
+
+    fn idx(i: usize) -> Val<I> {
+        i.into()
+    }
+
+    fn check_windows<O: Order<u32> + Clone>(v: &[u32], order: O) {
+        let x: IdxVec<Xs> = v.to_vec().into();
+        let rmq = NaiveRMQImpl::<Xs, O>::from_slice(&x, order.clone());
+        for w in 1..v.len() + 2 {
+            let win: SlidingWindow<Xs, Val<I>, O> = SlidingWindow::new(&x, w, order.clone());
+            assert_eq!((v.len() + 1).saturating_sub(w), win.len());
+            let res: Vec<_> = win.collect();
+            let expected: Vec<_> = (0..(v.len() + 1).saturating_sub(w))
+                .map(|i| {
+                    let p = rmq.rmq(idx(i)..idx(i + w));
+                    (idx(i), p.idx(), p.val())
+                })
+                .collect();
+            assert_eq!(expected, res);
+        }
+    }
+
+    #[test]
+    fn test_sliding_window() {
+        let v = vec![3, 1, 4, 1, 5, 9, 2, 6, 5, 3, 5, 8, 9, 7, 9];
+        check_windows(&v, Smallest);
+        check_windows(&v, Largest);
+        check_windows(&v, Rightmost(Smallest));
+        check_windows(&[4, 4, 4, 4], Smallest);
+        check_windows(&[4, 4, 4, 4], Rightmost(Largest));
+        check_windows(&[], Smallest);
+
+        // No windows, and no room made for them either
+        let x: IdxVec<Xs> = vec![1, 2, 3].into();
+        let mut win: SlidingWindow<Xs, Val<I>, _> = SlidingWindow::new(&x, usize::MAX, Smallest);
+        assert_eq!(0, win.len());
+        assert_eq!(None, win.next());
+    }
+
+    #[test]
+    fn test_sliding_min_max() {
+        let x: IdxVec<Xs> = vec![5, 2, 7, 2, 1, 8].into();
+        let w: Val<Len> = Val(3);
+        let mins: Vec<(Val<I>, Val<I>, u32)> = sliding_min(&x, w).collect();
+        assert_eq!(
+            vec![
+                (idx(0), idx(1), 2),
+                (idx(1), idx(1), 2),
+                (idx(2), idx(4), 1),
+                (idx(3), idx(4), 1),
+            ],
+            mins
+        );
+        let maxs: Vec<(Val<I>, Val<I>, u32)> = sliding_max(&x, w).collect();
+        let maxs: Vec<_> = maxs.iter().map(|&(_, j, x)| (j, x)).collect();
+        assert_eq!(
+            vec![(idx(2), 7), (idx(2), 7), (idx(2), 7), (idx(5), 8)],
+            maxs
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use crate::rmq::{adjusted_index, log_table_size, Pow};
+use crate::*;
+use std::ops::Range;
+
+/// An idempotent and associative operation, op(a,a) = a and
+/// op(op(a,b),c) = op(a,op(b,c)). For those, we can combine the
+/// results for two overlapping ranges without counting anything twice,
+/// which is what the sparse table needs.
+///
+/// Any closure Fn(&T, &T) -> T is an operation, but it is your job to
+/// make sure it is idempotent and associative.
+pub trait IdempotentOp<T> {
+    fn op(&self, a: &T, b: &T) -> T;
+}
+
+/// Minimum of values
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MinOp;
+
+/// Maximum of values
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MaxOp;
+
+/// Greatest common divisor of values
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GcdOp;
+
+/// Bitwise and of values
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AndOp;
+
+/// Bitwise or of values
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OrOp;
+
+impl<T: Ord + Copy> IdempotentOp<T> for MinOp {
+    #[inline]
+    fn op(&self, a: &T, b: &T) -> T {
+        std::cmp::min(*a, *b)
+    }
+}
+
+impl<T: Ord + Copy> IdempotentOp<T> for MaxOp {
+    #[inline]
+    fn op(&self, a: &T, b: &T) -> T {
+        std::cmp::max(*a, *b)
+    }
+}
+
+impl<T: num::Integer> IdempotentOp<T> for GcdOp {
+    #[inline]
+    fn op(&self, a: &T, b: &T) -> T {
+        a.gcd(b)
+    }
+}
+
+impl<T: std::ops::BitAnd<Output = T> + Copy> IdempotentOp<T> for AndOp {
+    #[inline]
+    fn op(&self, a: &T, b: &T) -> T {
+        *a & *b
+    }
+}
+
+impl<T: std::ops::BitOr<Output = T> + Copy> IdempotentOp<T> for OrOp {
+    #[inline]
+    fn op(&self, a: &T, b: &T) -> T {
+        *a | *b
+    }
+}
+
+impl<T, F> IdempotentOp<T> for F
+where
+    F: Fn(&T, &T) -> T,
+{
+    #[inline]
+    fn op(&self, a: &T, b: &T) -> T {
+        self(a, b)
+    }
+}
+
+/// Sparse table for an idempotent operation. We tabulate op over all
+/// ranges [i,i+2^k), and then we can get op over [i,j) from the two
+/// overlapping ranges [i,i+2^k) and [j-2^k,j) (where k is the largest
+/// such k), exactly as in the RMQ table. That gives us O(n log n)
+/// preprocessing and O(1) queries for minimum, maximum, gcd, bitwise
+/// and and or, or any other idempotent operation.
+pub struct SparseTable<_Tag: SeqTrait, Op> {
+    op: Op,
+    // levels[(Pow(k), i)] holds op over [i,i+2^k). Level 0 is the values
+    // themselves. Cells past n-2^k are not valid ranges and we just
+    // copy the level below into them.
+    levels: Table<Pow, usize, _Tag::Type>,
+}
+
+impl<_Tag, Op> SparseTable<_Tag, Op>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Clone,
+    Op: IdempotentOp<_Tag::Type>,
+{
+    pub fn new(values: IdxVec<_Tag>, op: Op) -> SparseTable<_Tag, Op> {
+        let n = values.len();
+        let logn = if n == 0 { 1 } else { log_table_size(n).0 };
+        let mut data = values.0;
+        data.reserve(n * (logn - 1));
+        // Dynamic programming from the shorter ranges to the longer,
+        // [i,i+2^k) = [i,i+2^{k-1}) [i+2^{k-1},i+2^k).
+        for k in 1..logn {
+            let half = Pow(k - 1).value();
+            let prev = (k - 1) * n;
+            for i in 0..n {
+                let x = if i + Pow(k).value() <= n {
+                    op.op(&data[prev + i], &data[prev + i + half])
+                } else {
+                    data[prev + i].clone()
+                };
+                data.push(x);
+            }
+        }
+        let levels = Table::from_vec(logn, n, data);
+        SparseTable { op, levels }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.levels.cols()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The values the table was built from.
+    #[inline]
+    pub fn values(&self) -> &IdxSlice<_Tag> {
+        self.levels.row(Pow(0)).into()
+    }
+
+    /// The operation the table combines values with.
+    #[inline]
+    pub fn op(&self) -> &Op {
+        &self.op
+    }
+
+    /// Get op over all the values in the range r. The range must be
+    /// non-empty.
+    pub fn query<Idx>(&self, r: Range<Idx>) -> _Tag::Type
+    where
+        Idx: IndexType + CanIndex<_Tag> + Copy,
+    {
+        let (i, j) = (r.start.index(), r.end.index());
+        assert!(i < j && j <= self.len(), "query needs a non-empty range");
+        let (Pow(k), ii) = adjusted_index(i, j);
+        self.op
+            .op(&self.levels[(Pow(k), i)], &self.levels[(Pow(k), ii)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rustfmt::skip]
+    mod types {
+        use crate::*;
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Xs();
+        impl SeqTrait for Xs { type Type = u32; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct I {}
+        impl TypeTrait for I { type Type = u32; }
+        impl CanIndex<Xs> for I {}
+    }
+    use types::*;
+
+    fn idx(i: usize) -> Val<I> {
+        i.into()
+    }
+
+    fn check_table<Op, F>(v: &[u32], op: Op, fold: F)
+    where
+        Op: IdempotentOp<u32>,
+        F: Fn(u32, u32) -> u32,
+    {
+        let tbl: SparseTable<Xs, Op> = SparseTable::new(v.to_vec().into(), op);
+        assert_eq!(v.len(), tbl.len());
+        for i in 0..v.len() {
+            for j in i + 1..v.len() + 1 {
+                let expected = v[i + 1..j].iter().fold(v[i], |a, b| fold(a, *b));
+                assert_eq!(expected, tbl.query(idx(i)..idx(j)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_sparse_table() {
+        let v = vec![12, 18, 6, 7, 28, 14, 42, 3, 9, 27, 81, 36, 24];
+        check_table(&v, MinOp, std::cmp::min);
+        check_table(&v, MaxOp, std::cmp::max);
+        check_table(&v, GcdOp, num::integer::gcd);
+        check_table(&v, AndOp, |a, b| a & b);
+        check_table(&v, OrOp, |a, b| a | b);
+        check_table(&v, |a: &u32, b: &u32| *a.min(b), std::cmp::min);
+
+        // Power of two and degenerate lengths
+        let v: Vec<u32> = (0..16).map(|i| (i * 7919) % 31).collect();
+        check_table(&v, MinOp, std::cmp::min);
+        check_table(&v, GcdOp, num::integer::gcd);
+        check_table(&[5], MaxOp, std::cmp::max);
+        check_table(&[], MaxOp, std::cmp::max);
+    }
+
+    #[test]
+    fn test_sparse_table_values() {
+        let tbl: SparseTable<Xs, _> = SparseTable::new(vec![4, 6, 8].into(), GcdOp);
+        assert_eq!(6, tbl.values()[idx(1)]);
+        assert_eq!(2, tbl.query(idx(0)..idx(3)));
+        assert_eq!(2, tbl.query(idx(1)..idx(3)));
+        assert_eq!(8, tbl.query(idx(2)..idx(3)));
+    }
+}
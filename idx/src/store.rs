@@ -0,0 +1,960 @@
+use crate::macros::*;
+use crate::rmq::{
+    checked_range, log_table_size, power_query, BlockParts, BlockRMQImpl, Largest, Order, Point,
+    PowerRMQImpl, Rightmost, Smallest, TwoD, MAX_BLOCK_SIZE,
+};
+use crate::*;
+use memmap2::Mmap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::path::Path;
+
+/// Values we can write to a file as their bytes, and read back by
+/// reinterpreting the bytes, which is what lets us use the tables in a
+/// memory-mapped file without copying them.
+///
+/// # Safety
+///
+/// Only implement this for types without padding, for which any bit
+/// pattern is a valid value, and with an alignment of at most eight.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! pod {
+    ($t:ty) => {
+        unsafe impl Pod for $t {}
+    };
+}
+apply_base_types!(pod);
+pod!(f32);
+pod!(f64);
+
+// Val is repr(transparent), so it is plain data if what it wraps is.
+unsafe impl<_Tag> Pod for Val<_Tag>
+where
+    _Tag: TypeTrait + Copy + 'static,
+    _Tag::Type: Pod,
+{
+}
+
+/// Orders we can record in a stored file. A file knows its order by the
+/// name of the order's type, so that name must say what the order is.
+/// Closures don't qualify: all the closures in a function get the same
+/// name, so a file built with one would open with any other. Give the
+/// comparison a named type that implements Order and StoredOrder instead.
+pub trait StoredOrder {}
+
+impl StoredOrder for Smallest {}
+impl StoredOrder for Largest {}
+impl<O: StoredOrder> StoredOrder for Rightmost<O> {}
+
+/// The version of the file format. Files from other versions are rejected.
+pub const FORMAT_VERSION: u32 = 1;
+
+const MAGIC: [u8; 8] = *b"IDXRMQ\0\0";
+// Written in native byte order, so we can tell if a file comes from a
+// machine with the other byte order.
+const BYTE_ORDER: u32 = 0x0102_0304;
+const HEADER_SIZE: usize = 96;
+// Sections start at multiples of this, so we can cast them in place.
+const SECTION_ALIGN: usize = 8;
+
+/// The kinds of RMQ structure we can store.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StoredKind {
+    /// A PowerRMQImpl, the sparse table over all positions.
+    Power = 1,
+    /// A BlockRMQImpl, with the sparse table over the blocks.
+    Block = 2,
+}
+
+/// Errors from writing or opening a stored RMQ structure.
+#[derive(Debug)]
+pub enum StoreError {
+    Io(std::io::Error),
+    /// The file doesn't start with our magic bytes.
+    NotAnRmqFile,
+    /// The file was written with another version of the format.
+    Version(u32),
+    /// The file was written on a machine with the other byte order.
+    ByteOrder,
+    /// The file holds another kind of structure than we asked for.
+    Kind {
+        expected: StoredKind,
+        found: u32,
+    },
+    /// The file was built for values of another width.
+    ValueWidth {
+        expected: usize,
+        found: usize,
+    },
+    /// The file was built for index cells of another width.
+    IndexWidth {
+        expected: usize,
+        found: usize,
+    },
+    /// The file was built for another value type of the same width.
+    ValueType {
+        expected: &'static str,
+    },
+    /// The file was built for another index cell type of the same width.
+    CellType {
+        expected: &'static str,
+    },
+    /// The file was built with another order.
+    Order {
+        expected: &'static str,
+    },
+    /// The header has a block size we don't support.
+    BlockSize(u64),
+    /// The header describes sections too large to address.
+    TooLarge,
+    /// The file is shorter or longer than its header says.
+    Size {
+        expected: usize,
+        found: usize,
+    },
+    /// The data doesn't match the checksum in the header.
+    Checksum,
+    /// Values or cells need stricter alignment than the format provides.
+    Alignment,
+}
+
+impl std::fmt::Display for StoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use StoreError::*;
+        match self {
+            Io(err) => write!(f, "i/o error: {}", err),
+            NotAnRmqFile => write!(f, "not an RMQ file"),
+            Version(v) => write!(f, "file format version {}, expected {}", v, FORMAT_VERSION),
+            ByteOrder => write!(f, "file was written with the other byte order"),
+            Kind { expected, found } => {
+                write!(
+                    f,
+                    "file holds structure kind {}, expected {:?}",
+                    found, expected
+                )
+            }
+            ValueWidth { expected, found } => write!(
+                f,
+                "file was built for {}-byte values, but opened for {}-byte values",
+                found, expected
+            ),
+            IndexWidth { expected, found } => write!(
+                f,
+                "file was built for {}-byte index cells, but opened for {}-byte cells",
+                found, expected
+            ),
+            ValueType { expected } => {
+                write!(f, "file was built for another value type than {}", expected)
+            }
+            CellType { expected } => {
+                write!(f, "file was built for another cell type than {}", expected)
+            }
+            Order { expected } => write!(f, "file was built with another order than {}", expected),
+            BlockSize(bs) => write!(
+                f,
+                "block size {} is not between 1 and {}",
+                bs, MAX_BLOCK_SIZE
+            ),
+            TooLarge => write!(f, "the header describes sections too large to address"),
+            Size { expected, found } => {
+                write!(
+                    f,
+                    "file is {} bytes, but the header says {}",
+                    found, expected
+                )
+            }
+            Checksum => write!(f, "checksum mismatch; the file is corrupted"),
+            Alignment => write!(f, "types need more than {}-byte alignment", SECTION_ALIGN),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StoreError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for StoreError {
+    fn from(err: std::io::Error) -> StoreError {
+        StoreError::Io(err)
+    }
+}
+
+// SECTION: The file format
+//
+// A file is a 96 byte header followed by a number of sections, each
+// starting at a multiple of SECTION_ALIGN. All numbers are in native
+// byte order. The header is
+//
+//   0..8    magic
+//   8..12   format version
+//   12..16  byte order mark
+//   16..20  kind
+//   20..24  value width in bytes
+//   24..28  index cell width in bytes
+//   28..32  reserved (zero)
+//   32..40  number of values, n
+//   40..48  block size (zero for Power)
+//   48..56  number of in-block tables (zero for Power)
+//   56..64  FNV-1a checksum of everything after the header
+//   64..72  hash of the value type's name
+//   72..80  hash of the index cell type's name
+//   80..88  hash of the order type's name
+//   88..96  reserved (zero)
+//
+// The widths catch most mismatches with a clear error, and the type
+// hashes catch the rest, like u32 values opened as f32, or a table built
+// with Largest opened with Smallest. Type names aren't guaranteed to be
+// stable between compiler versions, so a file may be rejected after an
+// upgrade. Two types can only share a name if they are closures, or
+// types with the same path from different versions of a crate, and
+// StoredOrder keeps closures out of the order, so only the latter can
+// open a file with the wrong types.
+//
+// For Power, the sections are the values and the sparse table. For Block,
+// they are the values, the block minima, the sparse table over the blocks,
+// the block types, and the in-block tables. The number of elements in
+// each section follows from the header.
+
+struct Header {
+    kind: u32,
+    value_width: u32,
+    index_width: u32,
+    n: u64,
+    bs: u64,
+    tables: u64,
+    checksum: u64,
+    value_type: u64,
+    cell_type: u64,
+    order: u64,
+}
+
+// FNV-1a of the type's name, as its identity in the header
+fn type_hash<T: ?Sized>() -> u64 {
+    let mut h = Fnv::new();
+    h.update(std::any::type_name::<T>().as_bytes());
+    h.0
+}
+
+impl Header {
+    fn to_bytes(&self) -> [u8; HEADER_SIZE] {
+        let mut buf = [0; HEADER_SIZE];
+        buf[0..8].copy_from_slice(&MAGIC);
+        buf[8..12].copy_from_slice(&FORMAT_VERSION.to_ne_bytes());
+        buf[12..16].copy_from_slice(&BYTE_ORDER.to_ne_bytes());
+        buf[16..20].copy_from_slice(&self.kind.to_ne_bytes());
+        buf[20..24].copy_from_slice(&self.value_width.to_ne_bytes());
+        buf[24..28].copy_from_slice(&self.index_width.to_ne_bytes());
+        buf[32..40].copy_from_slice(&self.n.to_ne_bytes());
+        buf[40..48].copy_from_slice(&self.bs.to_ne_bytes());
+        buf[48..56].copy_from_slice(&self.tables.to_ne_bytes());
+        buf[56..64].copy_from_slice(&self.checksum.to_ne_bytes());
+        buf[64..72].copy_from_slice(&self.value_type.to_ne_bytes());
+        buf[72..80].copy_from_slice(&self.cell_type.to_ne_bytes());
+        buf[80..88].copy_from_slice(&self.order.to_ne_bytes());
+        buf
+    }
+
+    fn from_bytes(buf: &[u8]) -> Result<Header, StoreError> {
+        if buf.len() < HEADER_SIZE || buf[0..8] != MAGIC {
+            return Err(StoreError::NotAnRmqFile);
+        }
+        let u32_at = |i: usize| u32::from_ne_bytes(buf[i..i + 4].try_into().unwrap());
+        let u64_at = |i: usize| u64::from_ne_bytes(buf[i..i + 8].try_into().unwrap());
+        if u32_at(12) != BYTE_ORDER {
+            return Err(StoreError::ByteOrder);
+        }
+        if u32_at(8) != FORMAT_VERSION {
+            return Err(StoreError::Version(u32_at(8)));
+        }
+        Ok(Header {
+            kind: u32_at(16),
+            value_width: u32_at(20),
+            index_width: u32_at(24),
+            n: u64_at(32),
+            bs: u64_at(40),
+            tables: u64_at(48),
+            checksum: u64_at(56),
+            value_type: u64_at(64),
+            cell_type: u64_at(72),
+            order: u64_at(80),
+        })
+    }
+
+    fn new<T, C, O>(kind: StoredKind, n: usize, bs: usize, tables: usize) -> Header {
+        Header {
+            kind: kind as u32,
+            value_width: std::mem::size_of::<T>() as u32,
+            index_width: std::mem::size_of::<C>() as u32,
+            n: n as u64,
+            bs: bs as u64,
+            tables: tables as u64,
+            checksum: 0,
+            value_type: type_hash::<T>(),
+            cell_type: type_hash::<C>(),
+            order: type_hash::<O>(),
+        }
+    }
+
+    /// Check that the header describes a structure of the given kind,
+    /// over values T with cells C, in order O.
+    fn check<T, C, O>(&self, kind: StoredKind) -> Result<(), StoreError> {
+        if self.kind != kind as u32 {
+            return Err(StoreError::Kind {
+                expected: kind,
+                found: self.kind,
+            });
+        }
+        let (vw, iw) = (std::mem::size_of::<T>(), std::mem::size_of::<C>());
+        if self.value_width as usize != vw {
+            let found = self.value_width as usize;
+            return Err(StoreError::ValueWidth {
+                expected: vw,
+                found,
+            });
+        }
+        if self.index_width as usize != iw {
+            let found = self.index_width as usize;
+            return Err(StoreError::IndexWidth {
+                expected: iw,
+                found,
+            });
+        }
+        use std::any::type_name;
+        if self.value_type != type_hash::<T>() {
+            let expected = type_name::<T>();
+            return Err(StoreError::ValueType { expected });
+        }
+        if self.cell_type != type_hash::<C>() {
+            let expected = type_name::<C>();
+            return Err(StoreError::CellType { expected });
+        }
+        if self.order != type_hash::<O>() {
+            let expected = type_name::<O>();
+            return Err(StoreError::Order { expected });
+        }
+        Ok(())
+    }
+
+    /// The number of values, if it fits in a usize.
+    fn len(&self) -> Result<usize, StoreError> {
+        usize::try_from(self.n).map_err(|_| StoreError::TooLarge)
+    }
+
+    /// The block size, if it is one we support.
+    fn block_size(&self) -> Result<usize, StoreError> {
+        if (1..=MAX_BLOCK_SIZE as u64).contains(&self.bs) {
+            Ok(self.bs as usize)
+        } else {
+            Err(StoreError::BlockSize(self.bs))
+        }
+    }
+}
+
+// The product of the numbers, if it doesn't overflow
+fn section_size(factors: &[usize]) -> Result<usize, StoreError> {
+    factors
+        .iter()
+        .try_fold(1usize, |acc, &x| acc.checked_mul(x))
+        .ok_or(StoreError::TooLarge)
+}
+
+#[inline]
+fn padding(len: usize) -> usize {
+    len.next_multiple_of(SECTION_ALIGN) - len
+}
+
+// FNV-1a, 64 bit
+struct Fnv(u64);
+impl Fnv {
+    fn new() -> Fnv {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+    fn update(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+fn check_alignment<T, C>() -> Result<(), StoreError> {
+    if std::mem::align_of::<T>() > SECTION_ALIGN || std::mem::align_of::<C>() > SECTION_ALIGN {
+        return Err(StoreError::Alignment);
+    }
+    Ok(())
+}
+
+fn as_bytes<T: Pod>(x: &[T]) -> &[u8] {
+    // Pod types have no padding, so all their bytes are initialised.
+    unsafe { std::slice::from_raw_parts(x.as_ptr() as *const u8, std::mem::size_of_val(x)) }
+}
+
+fn write_sections<W: Write>(
+    w: &mut W,
+    mut header: Header,
+    sections: &[&[u8]],
+) -> Result<(), StoreError> {
+    let zeros = [0u8; SECTION_ALIGN];
+    let mut checksum = Fnv::new();
+    for s in sections {
+        checksum.update(s);
+        checksum.update(&zeros[..padding(s.len())]);
+    }
+    header.checksum = checksum.0;
+    w.write_all(&header.to_bytes())?;
+    for s in sections {
+        w.write_all(s)?;
+        w.write_all(&zeros[..padding(s.len())])?;
+    }
+    w.flush()?;
+    Ok(())
+}
+
+fn save_to<P, F>(path: P, write: F) -> Result<(), StoreError>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut BufWriter<File>) -> Result<(), StoreError>,
+{
+    let mut w = BufWriter::new(File::create(path)?);
+    write(&mut w)
+}
+
+/// A memory-mapped file with its header checked, and the sections
+/// located.
+struct MappedFile {
+    map: Mmap,
+    header: Header,
+    // Byte offsets of the sections
+    sections: Vec<Range<usize>>,
+}
+
+impl MappedFile {
+    /// Map the file, check the header against what we expect, and check
+    /// that the sections, with the sizes in bytes we get from the header,
+    /// fit the file and match the checksum.
+    fn open<T, C, O, F>(path: &Path, kind: StoredKind, sizes: F) -> Result<MappedFile, StoreError>
+    where
+        F: FnOnce(&Header) -> Result<Vec<usize>, StoreError>,
+    {
+        check_alignment::<T, C>()?;
+        let file = File::open(path)?;
+        // The mapping is only valid as long as nobody modifies the file
+        // behind our back. We can't prevent that, but we do check the
+        // contents against the checksum before we use them.
+        let map = unsafe { Mmap::map(&file)? };
+        let header = Header::from_bytes(&map)?;
+        header.check::<T, C, O>(kind)?;
+
+        let mut sections = Vec::new();
+        let mut offset = HEADER_SIZE;
+        for size in sizes(&header)? {
+            let end = offset.checked_add(size).ok_or(StoreError::TooLarge)?;
+            sections.push(offset..end);
+            offset = end
+                .checked_next_multiple_of(SECTION_ALIGN)
+                .ok_or(StoreError::TooLarge)?;
+        }
+        if offset != map.len() {
+            return Err(StoreError::Size {
+                expected: offset,
+                found: map.len(),
+            });
+        }
+        let mut checksum = Fnv::new();
+        checksum.update(&map[HEADER_SIZE..]);
+        if checksum.0 != header.checksum {
+            return Err(StoreError::Checksum);
+        }
+        Ok(MappedFile {
+            map,
+            header,
+            sections,
+        })
+    }
+
+    /// Section s as a slice of T.
+    #[inline]
+    fn section<T: Pod>(&self, s: usize) -> &[T] {
+        let bytes = &self.map[self.sections[s].clone()];
+        // The map is page aligned and sections are SECTION_ALIGN aligned,
+        // which we checked is enough for T, and any bits make a valid T.
+        debug_assert_eq!(0, bytes.as_ptr() as usize % std::mem::align_of::<T>());
+        let len = bytes.len() / std::mem::size_of::<T>();
+        unsafe { std::slice::from_raw_parts(bytes.as_ptr() as *const T, len) }
+    }
+}
+
+// Columns in the sparse table over n positions
+fn table_cols(n: usize) -> usize {
+    match n {
+        0 => 0,
+        _ => log_table_size(n).0,
+    }
+}
+
+// SECTION: Sparse table RMQ
+
+impl<_Tag, O, C> PowerRMQImpl<_Tag, O, C>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Pod,
+    O: StoredOrder,
+    C: Pod,
+{
+    /// Write the structure in the stored format.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> Result<(), StoreError> {
+        check_alignment::<_Tag::Type, C>()?;
+        let values = self.values.as_slice();
+        let header = Header::new::<_Tag::Type, C, O>(StoredKind::Power, values.len(), 0, 0);
+        write_sections(
+            w,
+            header,
+            &[as_bytes(values), as_bytes(self.tbl.as_slice())],
+        )
+    }
+
+    /// Write the structure to a file we can later open with MappedPowerRMQ.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), StoreError> {
+        save_to(path, |w| self.write_to(w))
+    }
+}
+
+/// A PowerRMQImpl stored in a file and used straight from a memory map,
+/// so opening it doesn't copy or rebuild anything. The file records the
+/// type of the order, and opening it with another order is an error.
+pub struct MappedPowerRMQ<_Tag: SeqTrait, O = Smallest, C = usize> {
+    file: MappedFile,
+    order: O,
+    _types: PhantomData<fn() -> (_Tag, C)>,
+}
+
+impl<_Tag, C> MappedPowerRMQ<_Tag, Smallest, C>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Pod + Ord,
+    C: Pod + IndexType,
+{
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<MappedPowerRMQ<_Tag, Smallest, C>, StoreError> {
+        Self::open_with_order(path, Smallest)
+    }
+}
+
+impl<_Tag, O, C> MappedPowerRMQ<_Tag, O, C>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Pod,
+    O: Order<_Tag::Type> + StoredOrder,
+    C: Pod + IndexType,
+{
+    pub fn open_with_order<P: AsRef<Path>>(path: P, order: O) -> Result<Self, StoreError> {
+        let path = path.as_ref();
+        let file = MappedFile::open::<_Tag::Type, C, O, _>(path, StoredKind::Power, |h| {
+            let n = h.len()?;
+            let (vw, cw) = (std::mem::size_of::<_Tag::Type>(), std::mem::size_of::<C>());
+            Ok(vec![
+                section_size(&[n, vw])?,
+                section_size(&[n, table_cols(n), cw])?,
+            ])
+        })?;
+        Ok(MappedPowerRMQ {
+            file,
+            order,
+            _types: PhantomData,
+        })
+    }
+
+    #[inline]
+    pub fn values(&self) -> &IdxSlice<_Tag> {
+        self.file.section::<_Tag::Type>(0).into()
+    }
+
+    #[inline]
+    pub fn order(&self) -> &O {
+        &self.order
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.file.header.n as usize
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the first point in the range r. The range must be non-empty.
+    pub fn rmq<Idx>(&self, r: Range<Idx>) -> Point<Idx, _Tag::Type>
+    where
+        Idx: IndexType + CanIndex<_Tag> + Copy,
+    {
+        let (i, j) = checked_range(&r, self.len());
+        let x = self.values().as_slice();
+        let n = self.len();
+        let tbl: TwoD<C, &[C]> = TwoD::with_data(n, table_cols(n), self.file.section(1));
+        let m = power_query(&tbl, &self.order, |i| x[i], i, j);
+        Point(Idx::from_index(m), x[m])
+    }
+}
+
+// SECTION: Block RMQ
+
+impl<_Tag, O, C> BlockRMQImpl<_Tag, O, C>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Pod,
+    O: StoredOrder,
+    C: Pod,
+{
+    /// Write the structure in the stored format.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> Result<(), StoreError> {
+        check_alignment::<_Tag::Type, C>()?;
+        let values = self.values.as_slice();
+        let header = Header::new::<_Tag::Type, C, O>(
+            StoredKind::Block,
+            values.len(),
+            self.bs,
+            self.tables.rows(),
+        );
+        let sections = [
+            as_bytes(values),
+            as_bytes(&self.block_min),
+            as_bytes(self.block_tbl.as_slice()),
+            as_bytes(&self.block_type),
+            self.tables.as_slice(),
+        ];
+        write_sections(w, header, &sections)
+    }
+
+    /// Write the structure to a file we can later open with MappedBlockRMQ.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), StoreError> {
+        save_to(path, |w| self.write_to(w))
+    }
+}
+
+/// A BlockRMQImpl stored in a file and used straight from a memory map.
+/// As for MappedPowerRMQ, the order must be the one the file was built
+/// with.
+pub struct MappedBlockRMQ<_Tag: SeqTrait, O = Smallest, C = usize> {
+    file: MappedFile,
+    order: O,
+    // The block size from the header, checked when we opened the file
+    bs: usize,
+    _types: PhantomData<fn() -> (_Tag, C)>,
+}
+
+impl<_Tag, C> MappedBlockRMQ<_Tag, Smallest, C>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Pod + Ord,
+    C: Pod + IndexType,
+{
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<MappedBlockRMQ<_Tag, Smallest, C>, StoreError> {
+        Self::open_with_order(path, Smallest)
+    }
+}
+
+impl<_Tag, O, C> MappedBlockRMQ<_Tag, O, C>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Pod,
+    O: Order<_Tag::Type> + StoredOrder,
+    C: Pod + IndexType,
+{
+    pub fn open_with_order<P: AsRef<Path>>(path: P, order: O) -> Result<Self, StoreError> {
+        let path = path.as_ref();
+        let file = MappedFile::open::<_Tag::Type, C, O, _>(path, StoredKind::Block, |h| {
+            let (n, bs) = (h.len()?, h.block_size()?);
+            let tables = usize::try_from(h.tables).map_err(|_| StoreError::TooLarge)?;
+            let nb = n.div_ceil(bs);
+            let (vw, cw) = (std::mem::size_of::<_Tag::Type>(), std::mem::size_of::<C>());
+            Ok(vec![
+                section_size(&[n, vw])?,
+                section_size(&[nb, cw])?,
+                section_size(&[nb, table_cols(nb), cw])?,
+                section_size(&[nb, cw])?,
+                section_size(&[tables, bs, bs])?,
+            ])
+        })?;
+        let bs = file.header.block_size()?;
+        Ok(MappedBlockRMQ {
+            file,
+            order,
+            bs,
+            _types: PhantomData,
+        })
+    }
+
+    #[inline]
+    pub fn values(&self) -> &IdxSlice<_Tag> {
+        self.file.section::<_Tag::Type>(0).into()
+    }
+
+    #[inline]
+    pub fn order(&self) -> &O {
+        &self.order
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.file.header.n as usize
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The block size the RMQ was built with.
+    #[inline]
+    pub fn block_size(&self) -> usize {
+        self.bs
+    }
+
+    fn parts(&self) -> BlockParts<'_, _Tag::Type, C> {
+        let bs = self.block_size();
+        let block_min = self.file.section::<C>(1);
+        let nb = block_min.len();
+        let tables = self.file.section::<u8>(4);
+        BlockParts {
+            values: self.values().as_slice(),
+            bs,
+            block_min,
+            block_tbl: TwoD::with_data(nb, table_cols(nb), self.file.section(2)),
+            block_type: self.file.section(3),
+            tables: Table::with_data(tables.len() / (bs * bs), bs * bs, tables),
+        }
+    }
+
+    /// Get the first point in the range r. The range must be non-empty.
+    pub fn rmq<Idx>(&self, r: Range<Idx>) -> Point<Idx, _Tag::Type>
+    where
+        Idx: IndexType + CanIndex<_Tag> + Copy,
+    {
+        let (i, j) = checked_range(&r, self.len());
+        let m = self.parts().query(&self.order, i, j);
+        Point(Idx::from_index(m), self.values().as_slice()[m])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rmq::{Largest, RangeMinQuery};
+    use std::path::PathBuf;
+
+    #[rustfmt::skip]
+    mod types {
+        use crate::*;
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Lcp();
+        impl SeqTrait for Lcp { type Type = u32; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct I {}
+        impl TypeTrait for I { type Type = u32; }
+        impl CanIndex<Lcp> for I {}
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Floats();
+        impl SeqTrait for Floats { type Type = f32; }
+        impl CanIndex<Floats> for I {}
+    }
+    use types::*;
+
+    fn idx(i: usize) -> Val<I> {
+        i.into()
+    }
+
+    // A file in the temporary directory, removed when we are done with it.
+    struct TempFile(PathBuf);
+    impl TempFile {
+        fn new(name: &str) -> TempFile {
+            let name = format!("idx-store-{}-{}", std::process::id(), name);
+            TempFile(std::env::temp_dir().join(name))
+        }
+    }
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn values() -> IdxVec<Lcp> {
+        (0..200)
+            .map(|i| (i * 7919 + 5) % 37)
+            .collect::<Vec<u32>>()
+            .into()
+    }
+
+    // A named order, so we can store tables built with it
+    #[derive(Clone, Copy)]
+    struct TotalOrder;
+    impl Order<f32> for TotalOrder {
+        fn cmp(&self, a: &f32, b: &f32) -> std::cmp::Ordering {
+            a.total_cmp(b)
+        }
+    }
+    impl StoredOrder for TotalOrder {}
+
+    fn all_ranges(n: usize) -> impl Iterator<Item = Range<Val<I>>> {
+        (0..n).flat_map(move |i| (i + 1..n + 1).map(move |j| idx(i)..idx(j)))
+    }
+
+    #[test]
+    fn test_power_round_trip() {
+        let file = TempFile::new("power");
+        let rmq = PowerRMQImpl::<Lcp, Largest, u32>::build(values(), Largest);
+        rmq.save(&file.0).unwrap();
+
+        let mapped = MappedPowerRMQ::<Lcp, _, u32>::open_with_order(&file.0, Largest).unwrap();
+        assert_eq!(rmq.len(), mapped.len());
+        assert_eq!(rmq.values().as_slice(), mapped.values().as_slice());
+        for r in all_ranges(rmq.len()) {
+            let (Point(i1, x1), Point(i2, x2)) = (rmq.rmq(r.clone()), mapped.rmq(r));
+            assert_eq!((i1, x1), (i2, x2));
+        }
+
+        // Empty tables are fine as well
+        let empty = PowerRMQImpl::<Lcp>::new(vec![].into());
+        empty.save(&file.0).unwrap();
+        let mapped = MappedPowerRMQ::<Lcp>::open(&file.0).unwrap();
+        assert!(mapped.is_empty());
+
+        // Orders of our own, as long as they have a name
+        let floats: IdxVec<Floats> = vec![2.5, -1.0, f32::NAN, -0.0, 0.0].into();
+        let rmq = PowerRMQImpl::<Floats, _, u32>::build(floats, TotalOrder);
+        rmq.save(&file.0).unwrap();
+        let mapped =
+            MappedPowerRMQ::<Floats, _, u32>::open_with_order(&file.0, TotalOrder).unwrap();
+        assert_eq!(idx(1), mapped.rmq(idx(0)..idx(5)).idx());
+        let res = MappedPowerRMQ::<Floats, _, u32>::open_with_order(&file.0, Rightmost(TotalOrder));
+        assert!(matches!(res, Err(StoreError::Order { .. })));
+    }
+
+    #[test]
+    fn test_block_round_trip() {
+        let file = TempFile::new("block");
+        for bs in [1, 3, 8] {
+            let rmq =
+                BlockRMQImpl::<Lcp, Smallest, u16>::build_with_block_size(values(), Smallest, bs);
+            rmq.save(&file.0).unwrap();
+
+            let mapped = MappedBlockRMQ::<Lcp, Smallest, u16>::open(&file.0).unwrap();
+            assert_eq!(bs, mapped.block_size());
+            for r in all_ranges(rmq.len()) {
+                let (Point(i1, x1), Point(i2, x2)) = (rmq.rmq(r.clone()), mapped.rmq(r));
+                assert_eq!((i1, x1), (i2, x2));
+            }
+        }
+    }
+
+    #[test]
+    fn test_store_errors() {
+        let file = TempFile::new("errors");
+        let rmq = PowerRMQImpl::<Lcp, Smallest, u32>::build(values(), Smallest);
+        rmq.save(&file.0).unwrap();
+
+        // Wrong index width
+        let res = MappedPowerRMQ::<Lcp, Smallest, u64>::open(&file.0);
+        assert!(matches!(
+            res,
+            Err(StoreError::IndexWidth {
+                expected: 8,
+                found: 4
+            })
+        ));
+        // Wrong kind
+        let res = MappedBlockRMQ::<Lcp, Smallest, u32>::open(&file.0);
+        assert!(matches!(
+            res,
+            Err(StoreError::Kind {
+                expected: StoredKind::Block,
+                found: 1
+            })
+        ));
+        // Wrong value width
+        struct Wide();
+        impl SeqTrait for Wide {
+            type Type = u64;
+        }
+        let res = MappedPowerRMQ::<Wide, Smallest, u32>::open(&file.0);
+        assert!(matches!(
+            res,
+            Err(StoreError::ValueWidth {
+                expected: 8,
+                found: 4
+            })
+        ));
+
+        // Same widths, but other types
+        struct Signed();
+        impl SeqTrait for Signed {
+            type Type = i32;
+        }
+        let res = MappedPowerRMQ::<Signed, Smallest, u32>::open(&file.0);
+        assert!(matches!(
+            res,
+            Err(StoreError::ValueType { expected: "i32" })
+        ));
+        let res = MappedPowerRMQ::<Floats, _, u32>::open_with_order(&file.0, TotalOrder);
+        assert!(matches!(
+            res,
+            Err(StoreError::ValueType { expected: "f32" })
+        ));
+        let res = MappedPowerRMQ::<Lcp, Smallest, i32>::open(&file.0);
+        assert!(matches!(res, Err(StoreError::CellType { expected: "i32" })));
+        // Built with Smallest, opened with Largest
+        let res = MappedPowerRMQ::<Lcp, Largest, u32>::open_with_order(&file.0, Largest);
+        assert!(matches!(res, Err(StoreError::Order { .. })));
+
+        // Corrupted data
+        let mut bytes = std::fs::read(&file.0).unwrap();
+        bytes[HEADER_SIZE + 10] ^= 1;
+        std::fs::write(&file.0, &bytes).unwrap();
+        let res = MappedPowerRMQ::<Lcp, Smallest, u32>::open(&file.0);
+        assert!(matches!(res, Err(StoreError::Checksum)));
+
+        // Truncated data
+        bytes.truncate(bytes.len() - 8);
+        std::fs::write(&file.0, &bytes).unwrap();
+        let res = MappedPowerRMQ::<Lcp, Smallest, u32>::open(&file.0);
+        assert!(matches!(res, Err(StoreError::Size { .. })));
+
+        // Headers with block sizes we don't support, or sizes that overflow
+        let block = TempFile::new("errors-block");
+        let rmq = BlockRMQImpl::<Lcp, Smallest, u32>::build_with_block_size(values(), Smallest, 4);
+        rmq.save(&block.0).unwrap();
+        let good = std::fs::read(&block.0).unwrap();
+        for bs in [0, 32, u64::MAX] {
+            let mut bytes = good.clone();
+            bytes[40..48].copy_from_slice(&bs.to_ne_bytes());
+            std::fs::write(&block.0, &bytes).unwrap();
+            let res = MappedBlockRMQ::<Lcp, Smallest, u32>::open(&block.0);
+            assert!(matches!(res, Err(StoreError::BlockSize(found)) if found == bs));
+        }
+        for at in [32, 48] {
+            let mut bytes = good.clone();
+            bytes[at..at + 8].copy_from_slice(&u64::MAX.to_ne_bytes());
+            std::fs::write(&block.0, &bytes).unwrap();
+            let res = MappedBlockRMQ::<Lcp, Smallest, u32>::open(&block.0);
+            assert!(matches!(res, Err(StoreError::TooLarge)));
+        }
+
+        // Not our file at all
+        std::fs::write(&file.0, b"hello, world").unwrap();
+        let res = MappedPowerRMQ::<Lcp, Smallest, u32>::open(&file.0);
+        assert!(matches!(res, Err(StoreError::NotAnRmqFile)));
+
+        let res = MappedPowerRMQ::<Lcp, Smallest, u32>::open(file.0.join("missing"));
+        assert!(matches!(res, Err(StoreError::Io(_))));
+    }
+}
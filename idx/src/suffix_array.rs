@@ -0,0 +1,295 @@
+use crate::*;
+
+use num::Bounded;
+
+// The words SA-IS works on, for characters, positions and names alike.
+// The largest value marks an empty slot, so it can't be a position.
+trait Word: IndexType + Copy + Ord + Bounded {}
+impl<W: IndexType + Copy + Ord + Bounded> Word for W {}
+
+// Start (heads) or one past the end (tails) of each character's bucket
+fn buckets<W: Word>(s: &[W], bkt: &mut [W], tails: bool) {
+    bkt.fill(W::from_index(0));
+    for &c in s {
+        bkt[c.index()] = W::from_index(bkt[c.index()].index() + 1);
+    }
+    let mut sum = 0;
+    for b in bkt.iter_mut() {
+        let count = b.index();
+        sum += count;
+        *b = W::from_index(if tails { sum } else { sum - count });
+    }
+}
+
+/// Induced sorting. With the LMS suffixes placed at the ends of their
+/// buckets, in the order given by lms, we scan left to right to place the
+/// L-type suffixes, and then right to left to place the S-type suffixes.
+fn induce<W: Word>(s: &[W], k: usize, stype: &[bool], lms: &[W], sa: &mut [W]) {
+    let empty = W::max_value();
+    sa.fill(empty);
+    let mut bkt = vec![W::from_index(0); k];
+    buckets(s, &mut bkt, true);
+    for &i in lms.iter().rev() {
+        let c = s[i.index()].index();
+        let t = bkt[c].index() - 1;
+        bkt[c] = W::from_index(t);
+        sa[t] = i;
+    }
+
+    buckets(s, &mut bkt, false);
+    for r in 0..sa.len() {
+        let j = sa[r];
+        if j != empty && j.index() > 0 && !stype[j.index() - 1] {
+            let c = s[j.index() - 1].index();
+            let h = bkt[c].index();
+            sa[h] = W::from_index(j.index() - 1);
+            bkt[c] = W::from_index(h + 1);
+        }
+    }
+
+    buckets(s, &mut bkt, true);
+    for r in (0..sa.len()).rev() {
+        let j = sa[r];
+        if j != empty && j.index() > 0 && stype[j.index() - 1] {
+            let c = s[j.index() - 1].index();
+            let t = bkt[c].index() - 1;
+            bkt[c] = W::from_index(t);
+            sa[t] = W::from_index(j.index() - 1);
+        }
+    }
+}
+
+/// SA-IS on a string s over the alphabet [0,k) that ends with a unique
+/// sentinel, 0, smaller than all other characters.
+fn sais<W: Word>(s: &[W], k: usize) -> Vec<W> {
+    let n = s.len();
+    if n == 1 {
+        return vec![W::from_index(0)];
+    }
+
+    // Classify suffixes as S (smaller than the next suffix) or L (larger).
+    let mut stype = vec![false; n];
+    stype[n - 1] = true;
+    for i in (0..n - 1).rev() {
+        stype[i] = s[i] < s[i + 1] || (s[i] == s[i + 1] && stype[i + 1]);
+    }
+    let is_lms = |i: usize| i > 0 && stype[i] && !stype[i - 1];
+    let lms: Vec<W> = (1..n).filter(|&i| is_lms(i)).map(W::from_index).collect();
+
+    // Sort the LMS substrings by inducing from the LMS suffixes in any order.
+    let mut sa = vec![W::max_value(); n];
+    induce(s, k, &stype, &lms, &mut sa);
+
+    // Name the LMS substrings by their rank; equal substrings get the same name.
+    let lms_equal = |a: usize, b: usize| {
+        for d in 0.. {
+            if s[a + d] != s[b + d] || stype[a + d] != stype[b + d] {
+                return false;
+            }
+            if d > 0 && (is_lms(a + d) || is_lms(b + d)) {
+                return is_lms(a + d) && is_lms(b + d);
+            }
+        }
+        unreachable!()
+    };
+    let mut names = vec![W::max_value(); n];
+    let mut name = 0;
+    let mut prev = None;
+    for i in sa.iter().map(|i| i.index()).filter(|&i| is_lms(i)) {
+        if prev.is_some_and(|prev| !lms_equal(prev, i)) {
+            name += 1;
+        }
+        names[i] = W::from_index(name);
+        prev = Some(i);
+    }
+    let reduced: Vec<W> = lms.iter().map(|&i| names[i.index()]).collect();
+    drop(names);
+
+    // Sort the LMS suffixes, recursively if the names aren't unique.
+    let sorted_reduced = if name + 1 < reduced.len() {
+        sais(&reduced, name + 1)
+    } else {
+        let mut sa1 = vec![W::from_index(0); reduced.len()];
+        for (i, &c) in reduced.iter().enumerate() {
+            sa1[c.index()] = W::from_index(i);
+        }
+        sa1
+    };
+    let sorted_lms: Vec<W> = sorted_reduced.iter().map(|&i| lms[i.index()]).collect();
+
+    induce(s, k, &stype, &sorted_lms, &mut sa);
+    sa
+}
+
+// SA-IS on the n characters from chars, all in [1,k), followed by the
+// sentinel, with the rows from skip on mapped through f.
+fn sais_words<W: Word, T>(
+    chars: impl Iterator<Item = usize>,
+    n: usize,
+    k: usize,
+    skip: usize,
+    f: impl FnMut(usize) -> T,
+) -> Vec<T> {
+    let mut s: Vec<W> = Vec::with_capacity(n + 1);
+    s.extend(chars.map(W::from_index));
+    s.push(W::from_index(0));
+    let sa = sais(&s, k);
+    drop(s);
+    sa[skip..].iter().map(|i| i.index()).map(f).collect()
+}
+
+/// Sorts the suffixes of the n characters from chars, all in [1,k),
+/// followed by a sentinel 0, and returns f of their positions in sorted
+/// order, from row skip on. The work is done in u32 words when they can
+/// hold the positions and the characters, whatever type the caller wants
+/// in the end, and in usize words for larger inputs.
+pub(crate) fn sort_suffixes<T>(
+    chars: impl Iterator<Item = usize>,
+    n: usize,
+    k: usize,
+    skip: usize,
+    f: impl FnMut(usize) -> T,
+) -> Vec<T> {
+    // u32::MAX is the empty slot, so it must not be a position.
+    if n < u32::MAX as usize && k <= u32::MAX as usize {
+        sais_words::<u32, T>(chars, n, k, skip, f)
+    } else {
+        sais_words::<usize, T>(chars, n, k, skip, f)
+    }
+}
+
+/// Builds the suffix array of text with the SA-IS algorithm, in linear
+/// time. The suffix array holds the text positions of the suffixes in
+/// sorted order, as the index type Idx, so it can index back into the
+/// text, and the suffix array itself is a sequence _Sa that you index
+/// with its own index type. The index type decides the width of the
+/// result, so u32 positions for texts shorter than 2^32 take half the
+/// space of u64.
+///
+/// Construction doesn't depend on Idx: it runs on u32 words for texts
+/// shorter than 2^32 - 1 with characters below that, and on usize words
+/// otherwise. The working memory is about four words per character, plus
+/// a bit per character and the result.
+///
+/// Suffixes sort as if the text ended with a sentinel smaller than all
+/// characters, so a suffix comes before all the longer suffixes it is a
+/// prefix of. The characters are used as bucket indices, so the largest
+/// character decides the size of the buckets; map large alphabets to
+/// small integers first.
+pub fn suffix_array<_Text, _Sa, Idx>(text: &IdxSlice<_Text>) -> IdxVec<_Sa>
+where
+    _Text: SeqTrait,
+    _Text::Type: IndexType + Copy,
+    _Sa: SeqTrait<Type = Idx>,
+    Idx: IndexType + CanIndex<_Text> + Copy,
+{
+    // Shift the characters up by one to make room for the sentinel.
+    let x = text.as_slice();
+    let chars = x.iter().map(|c| c.index() + 1);
+    let k = chars.clone().max().map_or(1, |c| c + 1);
+    // The sentinel suffix is always first
+    sort_suffixes(chars, x.len(), k, 1, Idx::from_index).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rustfmt::skip]
+    mod types {
+        use crate::*;
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Text();
+        impl SeqTrait for Text { type Type = u8; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct TextIdx {}
+        impl TypeTrait for TextIdx { type Type = u32; }
+        impl CanIndex<Text> for TextIdx {}
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct WideIdx {}
+        impl TypeTrait for WideIdx { type Type = u64; }
+        impl CanIndex<Text> for WideIdx {}
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct SA();
+        impl SeqTrait for SA { type Type = Val<TextIdx>; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct WideSA();
+        impl SeqTrait for WideSA { type Type = Val<WideIdx>; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct SaIdx {}
+        impl TypeTrait for SaIdx { type Type = u32; }
+        impl CanIndex<SA> for SaIdx {}
+    }
+    use types::*;
+
+    fn naive_sa(x: &[u8]) -> Vec<usize> {
+        let mut sa: Vec<usize> = (0..x.len()).collect();
+        sa.sort_by(|&i, &j| x[i..].cmp(&x[j..]));
+        sa
+    }
+
+    fn check_sa(x: &[u8]) {
+        let text: IdxVec<Text> = x.to_vec().into();
+        let expected = naive_sa(x);
+        let sa: IdxVec<SA> = suffix_array(&text);
+        assert_eq!(expected, sa.0.iter().map(|i| i.index()).collect::<Vec<_>>());
+        let sa: IdxVec<WideSA> = suffix_array(&text);
+        assert_eq!(expected, sa.0.iter().map(|i| i.index()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_suffix_array() {
+        check_sa(b"mississippi");
+        check_sa(b"abracadabra");
+        check_sa(b"aaaaaaaaaa");
+        check_sa(b"abababababab");
+        check_sa(b"gattacagattacagattaca");
+        check_sa(b"a");
+        check_sa(b"");
+        check_sa(&[0, 0, 1, 0, 0, 255, 0]);
+        // Long enough for a few levels of recursion
+        let x: Vec<u8> = (0..2000u32)
+            .map(|i| b"acgt"[(i * i % 7 + i / 3) as usize % 4])
+            .collect();
+        check_sa(&x);
+    }
+
+    #[test]
+    fn test_word_widths() {
+        let x: Vec<usize> = (0..3000usize)
+            .map(|i| 1 + (i * i % 11 + i / 7) % 5)
+            .chain([0])
+            .collect();
+        let narrow: Vec<u32> = x.iter().map(|&c| c as u32).collect();
+        let wide = sais(&x, 6);
+        assert_eq!(
+            naive_sa(&x.iter().map(|&c| c as u8).collect::<Vec<_>>()),
+            wide
+        );
+        assert_eq!(
+            wide,
+            sais(&narrow, 6)
+                .iter()
+                .map(|&i| i as usize)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_typed_sa() {
+        let text: IdxVec<Text> = b"banana".to_vec().into();
+        let sa: IdxVec<SA> = suffix_array(&text);
+        // sa[SaIdx] is a text position we can index the text with
+        let i: Val<SaIdx> = Val(0);
+        let pos: Val<TextIdx> = sa[i];
+        assert_eq!(Val::<TextIdx>(5), pos);
+        assert_eq!(b'a', text[pos]);
+        assert_eq!(b"nana", &text[sa[Val::<SaIdx>(5)]..Val(6)].as_slice());
+    }
+}
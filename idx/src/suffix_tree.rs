@@ -0,0 +1,305 @@
+use crate::*;
+use std::ops::Range;
+
+// A node in the arena
+struct Node<TextIdx, NodeIdx> {
+    parent: Option<NodeIdx>,
+    // Length of the string from the root to the node
+    depth: usize,
+    // Start of a suffix in the node's subtree, so the path from the root
+    // spells text[start..start + depth].
+    start: TextIdx,
+    // The suffix that ends at the node, if any
+    suffix: Option<TextIdx>,
+    children: Vec<NodeIdx>,
+}
+
+/// A compact suffix tree, built from the suffix array and LCP array in
+/// linear time. The nodes live in an arena and are addressed by the index
+/// type NodeIdx, and the edge labels are ranges of text positions, TextIdx,
+/// so the tree doesn't hold on to the text.
+///
+/// There is no sentinel at the end of the text, so a suffix that is a
+/// prefix of another suffix ends at an internal node instead of a leaf,
+/// and that node can have a single child. Every other internal node has
+/// at least two children, and every leaf is a suffix. The children of a
+/// node are in lexicographical order.
+pub struct SuffixTree<TextIdx, NodeIdx> {
+    nodes: Vec<Node<TextIdx, NodeIdx>>,
+}
+
+impl<TextIdx, NodeIdx> SuffixTree<TextIdx, NodeIdx>
+where
+    TextIdx: IndexType + Copy,
+    NodeIdx: IndexType + Copy,
+{
+    /// Builds the tree from the suffix array sa and the LCP array lcp, as
+    /// from crate::suffix_array and crate::lcp. We add the suffixes in
+    /// suffix array order, keeping the path to the last leaf on a stack;
+    /// lcp[r] tells us where the path to suffix sa[r] branches off it.
+    pub fn new<_Sa, _Lcp, Len>(
+        sa: &IdxSlice<_Sa>,
+        lcp: &IdxSlice<_Lcp>,
+    ) -> SuffixTree<TextIdx, NodeIdx>
+    where
+        _Sa: SeqTrait<Type = TextIdx>,
+        _Lcp: SeqTrait<Type = Len>,
+        Len: IndexType + Copy,
+    {
+        let (sa, lcp) = (sa.as_slice(), lcp.as_slice());
+        let n = sa.len();
+        assert_eq!(
+            n,
+            lcp.len(),
+            "the LCP array must have the suffix array's length"
+        );
+
+        let mut tree = SuffixTree {
+            nodes: Vec::with_capacity(2 * n + 1),
+        };
+        let root = tree.push(None, 0, TextIdx::from_index(0), None);
+        let mut stack = vec![root];
+        for (r, &i) in sa.iter().enumerate() {
+            let l = if r == 0 { 0 } else { lcp[r].index() };
+            let mut last = None;
+            while tree.node(*stack.last().unwrap()).depth > l {
+                last = stack.pop();
+            }
+            let top = *stack.last().unwrap();
+            if tree.node(top).depth < l {
+                // The path branches inside the edge to last, so split it.
+                let last = last.unwrap();
+                tree.node_mut(top).children.pop();
+                let mid = tree.push(Some(top), l, i, None);
+                tree.node_mut(mid).children.push(last);
+                tree.node_mut(last).parent = Some(mid);
+                stack.push(mid);
+            }
+            let parent = *stack.last().unwrap();
+            let leaf = tree.push(Some(parent), n - i.index(), i, Some(i));
+            stack.push(leaf);
+        }
+        tree
+    }
+
+    fn push(
+        &mut self,
+        parent: Option<NodeIdx>,
+        depth: usize,
+        start: TextIdx,
+        suffix: Option<TextIdx>,
+    ) -> NodeIdx {
+        let v = NodeIdx::from_index(self.nodes.len());
+        self.nodes.push(Node {
+            parent,
+            depth,
+            start,
+            suffix,
+            children: vec![],
+        });
+        if let Some(p) = parent {
+            self.node_mut(p).children.push(v);
+        }
+        v
+    }
+
+    #[inline]
+    fn node(&self, v: NodeIdx) -> &Node<TextIdx, NodeIdx> {
+        &self.nodes[v.index()]
+    }
+
+    #[inline]
+    fn node_mut(&mut self, v: NodeIdx) -> &mut Node<TextIdx, NodeIdx> {
+        &mut self.nodes[v.index()]
+    }
+
+    /// The number of nodes, which is at most 2n for a text of length n > 0.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Never true; there is always the root.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    #[inline]
+    pub fn root(&self) -> NodeIdx {
+        NodeIdx::from_index(0)
+    }
+
+    #[inline]
+    pub fn parent(&self, v: NodeIdx) -> Option<NodeIdx> {
+        self.node(v).parent
+    }
+
+    #[inline]
+    pub fn children(&self, v: NodeIdx) -> &[NodeIdx] {
+        &self.node(v).children
+    }
+
+    #[inline]
+    pub fn is_leaf(&self, v: NodeIdx) -> bool {
+        self.node(v).children.is_empty()
+    }
+
+    /// The string depth of v, the length of the path label from the root.
+    #[inline]
+    pub fn depth(&self, v: NodeIdx) -> usize {
+        self.node(v).depth
+    }
+
+    /// The label on the edge into v, as a range in the text. The root
+    /// has an empty label.
+    pub fn label(&self, v: NodeIdx) -> Range<TextIdx> {
+        let node = self.node(v);
+        let up = node.parent.map_or(0, |p| self.node(p).depth);
+        let start = node.start.index();
+        TextIdx::from_index(start + up)..TextIdx::from_index(start + node.depth)
+    }
+
+    /// The path label of v, from the root, as a range in the text.
+    pub fn path_label(&self, v: NodeIdx) -> Range<TextIdx> {
+        let node = self.node(v);
+        let start = node.start.index();
+        TextIdx::from_index(start)..TextIdx::from_index(start + node.depth)
+    }
+
+    /// The start of the suffix that ends at v. All leaves have one, and
+    /// internal nodes have one when the suffix is a prefix of another.
+    #[inline]
+    pub fn suffix(&self, v: NodeIdx) -> Option<TextIdx> {
+        self.node(v).suffix
+    }
+
+    /// The suffixes in the subtree of v, in lexicographical order.
+    pub fn suffixes(&self, v: NodeIdx) -> Vec<TextIdx> {
+        let mut res = vec![];
+        let mut stack = vec![v];
+        while let Some(v) = stack.pop() {
+            res.extend(self.node(v).suffix);
+            stack.extend(self.node(v).children.iter().rev());
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lcp::lcp_kasai;
+    use crate::suffix_array::suffix_array;
+
+    #[rustfmt::skip]
+    mod types {
+        use crate::*;
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Text();
+        impl SeqTrait for Text { type Type = u8; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct TextIdx {}
+        impl TypeTrait for TextIdx { type Type = u32; }
+        impl CanIndex<Text> for TextIdx {}
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct SA();
+        impl SeqTrait for SA { type Type = Val<TextIdx>; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Lcp();
+        impl SeqTrait for Lcp { type Type = u32; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct NodeIdx {}
+        impl TypeTrait for NodeIdx { type Type = u32; }
+    }
+    use types::*;
+
+    type Tree = SuffixTree<Val<TextIdx>, Val<NodeIdx>>;
+
+    fn tree(text: &IdxSlice<Text>) -> Tree {
+        let sa: IdxVec<SA> = suffix_array(text);
+        let lcp: IdxVec<Lcp> = lcp_kasai(text, &sa);
+        Tree::new(&sa, &lcp)
+    }
+
+    fn check_tree(x: &[u8]) {
+        let text: IdxVec<Text> = x.to_vec().into();
+        let st = tree(&text);
+        let n = x.len();
+        assert!(st.len() <= usize::max(1, 2 * n));
+
+        // The suffixes come out in sorted order, once each
+        let mut sorted: Vec<usize> = (0..n).collect();
+        sorted.sort_by(|&i, &j| x[i..].cmp(&x[j..]));
+        let found: Vec<usize> = st.suffixes(st.root()).iter().map(|i| i.index()).collect();
+        assert_eq!(sorted, found);
+
+        for v in (0..st.len()).map(Val::<NodeIdx>::from) {
+            let label = st.label(v);
+            if let Some(p) = st.parent(v) {
+                assert!(st.children(p).contains(&v));
+                assert!(!label.is_empty());
+                // The path label extends the parent's
+                let (path, up) = (st.path_label(v), st.path_label(p));
+                let (i, j) = (path.start.index(), up.start.index());
+                let d = st.depth(p);
+                assert_eq!(x[i..i + d], x[j..j + d]);
+                assert_eq!((i + d, path.end), (label.start.index(), label.end));
+            } else {
+                assert!(label.is_empty());
+            }
+            // A suffix ends where its path label ends
+            if let Some(i) = st.suffix(v) {
+                assert_eq!(&x[i.index()..], text[st.path_label(v)].as_slice());
+            }
+            // Branching nodes, with children starting with distinct
+            // characters in increasing order
+            let cs = st.children(v);
+            if st.suffix(v).is_none() && v != st.root() {
+                assert!(cs.len() >= 2);
+            }
+            let firsts: Vec<u8> = cs.iter().map(|&c| text[st.label(c).start]).collect();
+            assert!(firsts.windows(2).all(|w| w[0] < w[1]));
+        }
+    }
+
+    #[test]
+    fn test_suffix_tree() {
+        check_tree(b"mississippi");
+        check_tree(b"abracadabra");
+        check_tree(b"aaaaaaa");
+        check_tree(b"abababab");
+        check_tree(b"a");
+        check_tree(b"");
+        let x: Vec<u8> = (0..500u32)
+            .map(|i| b"acgt"[(i * i % 7 + i / 3) as usize % 4])
+            .collect();
+        check_tree(&x);
+    }
+
+    #[test]
+    fn test_typed_nodes() {
+        let text: IdxVec<Text> = b"banana".to_vec().into();
+        let st = tree(&text);
+        let root = st.root();
+        // a, banana, na
+        let firsts: Vec<u8> = st
+            .children(root)
+            .iter()
+            .map(|&c| text[st.label(c).start])
+            .collect();
+        assert_eq!(b"abn", firsts.as_slice());
+
+        let a: Val<NodeIdx> = st.children(root)[0];
+        assert_eq!(Some(Val::<TextIdx>(5)), st.suffix(a));
+        assert_eq!(1, st.depth(a));
+        let ana = st.children(a)[0];
+        assert_eq!(b"na", text[st.label(ana)].as_slice());
+        assert_eq!(vec![Val::<TextIdx>(3), Val(1)], st.suffixes(ana));
+    }
+}
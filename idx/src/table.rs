@@ -0,0 +1,226 @@
+use crate::*;
+use std::marker::PhantomData;
+use std::ops::{Index, IndexMut};
+
+/// Types we can use as row or column indices in a Table. Any index type
+/// works, but we can also add our own, like the powers of two we use in
+/// the RMQ tables, so we do not confuse log-space with linear space.
+pub trait TableIndex: Copy {
+    fn table_index(self) -> usize;
+}
+
+impl<I: IndexType + Copy> TableIndex for I {
+    #[inline]
+    fn table_index(self) -> usize {
+        self.index()
+    }
+}
+
+/// A two-dimensional table stored as one contiguous vector in row-major
+/// order, and indexed by pairs (R, C) of typed indices. The cell type T
+/// can be anything, so we can use u32 cells where usize would waste space.
+///
+/// The cells live in S, which is a Vec<T> unless we need a view of
+/// cells that live somewhere else, like a slice of a memory-mapped file.
+pub struct Table<R, C, T, S = Vec<T>> {
+    rows: usize,
+    cols: usize,
+    data: S,
+    // fn(R, C) rather than (R, C) so the table doesn't own R or C values.
+    _index: PhantomData<fn(R, C) -> T>,
+}
+
+impl<R, C, T> Table<R, C, T>
+where
+    R: TableIndex,
+    C: TableIndex,
+{
+    /// A rows x cols table with default values in all cells.
+    pub fn new(rows: usize, cols: usize) -> Table<R, C, T>
+    where
+        T: Default + Clone,
+    {
+        Self::filled(rows, cols, T::default())
+    }
+
+    /// A rows x cols table with x in all cells.
+    pub fn filled(rows: usize, cols: usize, x: T) -> Table<R, C, T>
+    where
+        T: Clone,
+    {
+        Self::from_vec(rows, cols, vec![x; rows * cols])
+    }
+
+    /// A rows x cols table with the cells in data in row-major order.
+    pub fn from_vec(rows: usize, cols: usize, data: Vec<T>) -> Table<R, C, T> {
+        Self::with_data(rows, cols, data)
+    }
+
+    #[inline]
+    pub fn row_mut(&mut self, r: R) -> &mut [T] {
+        let start = r.table_index() * self.cols;
+        &mut self.data[start..start + self.cols]
+    }
+}
+
+impl<R, C, T, S> Table<R, C, T, S>
+where
+    R: TableIndex,
+    C: TableIndex,
+    S: AsRef<[T]>,
+{
+    /// A rows x cols table over the cells in data, in row-major order.
+    pub fn with_data(rows: usize, cols: usize, data: S) -> Table<R, C, T, S> {
+        assert_eq!(
+            rows * cols,
+            data.as_ref().len(),
+            "data doesn't match the table size"
+        );
+        Table {
+            rows,
+            cols,
+            data,
+            _index: PhantomData,
+        }
+    }
+
+    /// A table that borrows the cells of this one.
+    #[inline]
+    pub fn view(&self) -> Table<R, C, T, &[T]> {
+        Table::with_data(self.rows, self.cols, self.as_slice())
+    }
+
+    #[inline]
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    #[inline]
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// All the cells, in row-major order.
+    #[inline]
+    pub fn as_slice(&self) -> &[T] {
+        self.data.as_ref()
+    }
+
+    #[inline]
+    pub fn row(&self, r: R) -> &[T] {
+        let start = r.table_index() * self.cols;
+        &self.as_slice()[start..start + self.cols]
+    }
+
+    #[inline]
+    fn offset(&self, (r, c): (R, C)) -> usize {
+        let (r, c) = (r.table_index(), c.table_index());
+        // A row past the end lands outside the cells, where indexing them
+        // panics, but a column past the end would land in the next row.
+        assert!(c < self.cols, "table column out of bounds");
+        debug_assert!(r < self.rows, "table row out of bounds");
+        r * self.cols + c
+    }
+}
+
+impl<R, C, T, S> Index<(R, C)> for Table<R, C, T, S>
+where
+    R: TableIndex,
+    C: TableIndex,
+    S: AsRef<[T]>,
+{
+    type Output = T;
+    #[inline]
+    fn index(&self, index: (R, C)) -> &Self::Output {
+        &self.as_slice()[self.offset(index)]
+    }
+}
+
+impl<R, C, T, S> IndexMut<(R, C)> for Table<R, C, T, S>
+where
+    R: TableIndex,
+    C: TableIndex,
+    S: AsRef<[T]> + AsMut<[T]>,
+{
+    #[inline]
+    fn index_mut(&mut self, index: (R, C)) -> &mut Self::Output {
+        let offset = self.offset(index);
+        &mut self.data.as_mut()[offset]
+    }
+}
+
+impl<R, C, T, S> std::fmt::Display for Table<R, C, T, S>
+where
+    T: std::fmt::Display,
+    S: AsRef<[T]>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for row in self.data.as_ref().chunks(self.cols.max(1)) {
+            for val in row {
+                write!(f, "{} ", val)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    #[rustfmt::skip]
+    mod types {
+        use crate::*;
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Row {}
+        impl TypeTrait for Row { type Type = u16; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Col {}
+        impl TypeTrait for Col { type Type = i32; }
+    }
+    use types::*;
+
+    #[test]
+    fn test_table() {
+        let mut tbl: Table<Val<Row>, Val<Col>, u32> = Table::new(3, 4);
+        assert_eq!((3, 4), (tbl.rows(), tbl.cols()));
+        for r in 0..3u16 {
+            for c in 0..4i32 {
+                assert_eq!(0, tbl[(Val(r), Val(c))]);
+                tbl[(Val(r), Val(c))] = 10 * r as u32 + c as u32;
+            }
+        }
+        assert_eq!(&[10, 11, 12, 13], tbl.row(Val(1)));
+        assert_eq!(23, tbl[(Val(2), Val(3))]);
+        // Row-major layout
+        assert_eq!(12, tbl.as_slice()[4 + 2]);
+        tbl.row_mut(Val(0)).fill(7);
+        assert_eq!(&[7, 7, 7, 7], tbl.row(Val(0)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_column_out_of_bounds() {
+        let tbl: Table<usize, usize, u32> = Table::new(3, 4);
+        let _ = tbl[(0, 4)];
+    }
+
+    #[test]
+    fn test_from_vec() {
+        let tbl: Table<usize, usize, u8> = Table::from_vec(2, 3, vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(6, tbl[(1, 2)]);
+        assert_eq!(&[4, 5, 6], tbl.row(1));
+        let empty: Table<usize, usize, u8> = Table::from_vec(0, 0, vec![]);
+        assert_eq!(0, empty.rows());
+
+        // Views share the cells
+        let view = tbl.view();
+        assert_eq!(4, view[(1, 0)]);
+        let cells = [1u8, 2, 3, 4];
+        let tbl: Table<usize, usize, u8, &[u8]> = Table::with_data(2, 2, &cells);
+        assert_eq!(3, tbl[(1, 0)]);
+    }
+}
@@ -0,0 +1,313 @@
+use crate::rmq::{first, Order, Point, RangeMinQuery, Smallest};
+use crate::wavelet::WaveletCodes;
+use crate::*;
+use std::ops::Range;
+
+// A candidate for top_k: the first point in the range [start,end).
+struct Candidate<T> {
+    point: Point<usize, T>,
+    start: usize,
+    end: usize,
+}
+
+/// Binary heap of candidates, ordered by an Order on the values and the
+/// positions for ties. std's BinaryHeap wants Ord on the elements, but
+/// our orders are values we carry around, so we do the sifting here.
+struct CandidateHeap<'a, T, O> {
+    order: &'a O,
+    heap: Vec<Candidate<T>>,
+}
+
+impl<'a, T: Copy, O: Order<T>> CandidateHeap<'a, T, O> {
+    fn new(order: &'a O) -> Self {
+        CandidateHeap {
+            order,
+            heap: vec![],
+        }
+    }
+
+    // Does candidate a come before b?
+    #[inline]
+    fn before(&self, a: usize, b: usize) -> bool {
+        let (p, q) = (self.heap[a].point, self.heap[b].point);
+        first(self.order, p, q).idx() == p.idx()
+    }
+
+    fn push(&mut self, c: Candidate<T>) {
+        self.heap.push(c);
+        let mut i = self.heap.len() - 1;
+        while i > 0 && self.before(i, (i - 1) / 2) {
+            self.heap.swap(i, (i - 1) / 2);
+            i = (i - 1) / 2;
+        }
+    }
+
+    fn pop(&mut self) -> Option<Candidate<T>> {
+        if self.heap.is_empty() {
+            return None;
+        }
+        let top = self.heap.swap_remove(0);
+        let mut i = 0;
+        loop {
+            let (l, r) = (2 * i + 1, 2 * i + 2);
+            let mut m = i;
+            if l < self.heap.len() && self.before(l, m) {
+                m = l;
+            }
+            if r < self.heap.len() && self.before(r, m) {
+                m = r;
+            }
+            if m == i {
+                break;
+            }
+            self.heap.swap(i, m);
+            i = m;
+        }
+        Some(top)
+    }
+}
+
+/// The k first points in the range r, according to the order of the RMQ,
+/// and in that order. If the range has fewer than k values, you get all of
+/// them.
+///
+/// The first point in r splits it into two ranges, and the second point
+/// is the first in one of those, and so on. We keep the first point of
+/// each range we have split off in a heap, so we get the k points with
+/// 2k RMQs and O(k log k) work on the heap.
+pub fn top_k<_Tag, R, Idx>(rmq: &R, r: Range<Idx>, k: usize) -> Vec<Point<Idx, _Tag::Type>>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Copy,
+    R: RangeMinQuery<_Tag>,
+    Idx: IndexType + CanIndex<_Tag> + Copy,
+{
+    let (i, j) = (r.start.index(), r.end.index());
+    assert!(i <= j && j <= rmq.len(), "range out of bounds");
+    let candidate = |start: usize, end: usize| {
+        let Point(m, x) = rmq.rmq::<Idx>(Idx::from_index(start)..Idx::from_index(end));
+        Candidate {
+            point: Point(m.index(), x),
+            start,
+            end,
+        }
+    };
+
+    let mut res = Vec::with_capacity(usize::min(k, j - i));
+    let mut heap = CandidateHeap::new(rmq.order());
+    if i < j {
+        heap.push(candidate(i, j));
+    }
+    while res.len() < k {
+        let Some(Candidate { point, start, end }) = heap.pop() else {
+            break;
+        };
+        let Point(m, x) = point;
+        res.push(Point(Idx::from_index(m), x));
+        if start < m {
+            heap.push(candidate(start, m));
+        }
+        if m + 1 < end {
+            heap.push(candidate(m + 1, end));
+        }
+    }
+    res
+}
+
+/// Wavelet matrix for range k-th smallest queries. We rank the values by
+/// an order, with ties broken by position, and store the ranks bit by bit
+/// from the most significant. At each level, the positions with a zero
+/// bit go first and those with a one bit after, keeping their relative
+/// order, and each level is a bit vector with rank. A query follows the
+/// range down through the levels, going into the zeros or ones depending
+/// on how many zeros the range holds, and picks up the bits of the rank
+/// on the way.
+///
+/// The levels take ceil(log2 n) bits per value, plus an eighth for rank.
+/// To turn a rank back into a position we also keep the positions sorted
+/// by rank, a word per value, next to the values themselves. Building it
+/// takes O(n log n) time, and queries O(log n).
+pub struct WaveletMatrix<_Tag: SeqTrait, O = Smallest> {
+    values: IdxVec<_Tag>,
+    order: O,
+    // Positions of the values, sorted by rank
+    sorted: Vec<usize>,
+    // The bits of the ranks, in position order
+    levels: WaveletCodes,
+}
+
+impl<_Tag> WaveletMatrix<_Tag>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Ord + Copy,
+{
+    pub fn new(values: IdxVec<_Tag>) -> WaveletMatrix<_Tag> {
+        Self::with_order(values, Smallest)
+    }
+}
+
+impl<_Tag, O> WaveletMatrix<_Tag, O>
+where
+    _Tag: SeqTrait,
+    _Tag::Type: Copy,
+    O: Order<_Tag::Type>,
+{
+    pub fn with_order(values: IdxVec<_Tag>, order: O) -> WaveletMatrix<_Tag, O> {
+        let x = values.as_slice();
+        let n = x.len();
+        let mut sorted: Vec<usize> = (0..n).collect();
+        sorted.sort_by(|&a, &b| {
+            let ties = if order.rightmost() {
+                b.cmp(&a)
+            } else {
+                a.cmp(&b)
+            };
+            order.cmp(&x[a], &x[b]).then(ties)
+        });
+        let mut rank = vec![0; n];
+        for (r, &i) in sorted.iter().enumerate() {
+            rank[i] = r;
+        }
+
+        let width = match n {
+            0 | 1 => 0,
+            _ => (n - 1).ilog2() + 1,
+        };
+        let levels = WaveletCodes::new(rank, width);
+
+        WaveletMatrix {
+            values,
+            order,
+            sorted,
+            levels,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    #[inline]
+    pub fn values(&self) -> &IdxSlice<_Tag> {
+        &self.values
+    }
+
+    #[inline]
+    pub fn order(&self) -> &O {
+        &self.order
+    }
+
+    /// The k-th smallest point in r, counting from zero, according to the
+    /// order, with ties broken by position. k must be less than the length
+    /// of r.
+    pub fn kth<Idx>(&self, r: Range<Idx>, k: usize) -> Point<Idx, _Tag::Type>
+    where
+        Idx: IndexType + CanIndex<_Tag> + Copy,
+    {
+        let (i, j) = (r.start.index(), r.end.index());
+        assert!(i <= j && j <= self.len(), "range out of bounds");
+        assert!(k < j - i, "k must be less than the length of the range");
+        let m = self.sorted[self.levels.kth(i, j, k)];
+        Point(Idx::from_index(m), self.values.as_slice()[m])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rmq::{BlockRMQImpl, Largest, NaiveRMQImpl, PowerRMQImpl, Rightmost};
+
+    #[rustfmt::skip]
+    mod types {
+        use crate::*;
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct Lcp();
+        impl SeqTrait for Lcp { type Type = u32; }
+
+        #[derive(Clone, Copy, Debug)]
+        pub struct I {}
+        impl TypeTrait for I { type Type = u32; }
+        impl CanIndex<Lcp> for I {}
+    }
+    use types::*;
+
+    fn idx(i: usize) -> Val<I> {
+        i.into()
+    }
+
+    // All the points in [i,j), sorted by order, then position
+    fn sorted<O: Order<u32>>(v: &[u32], order: &O, i: usize, j: usize) -> Vec<(Val<I>, u32)> {
+        let mut pos: Vec<usize> = (i..j).collect();
+        pos.sort_by(|&a, &b| {
+            let ties = if order.rightmost() {
+                b.cmp(&a)
+            } else {
+                a.cmp(&b)
+            };
+            order.cmp(&v[a], &v[b]).then(ties)
+        });
+        pos.iter().map(|&p| (idx(p), v[p])).collect()
+    }
+
+    fn check<R>(rmq: &R)
+    where
+        R: RangeMinQuery<Lcp>,
+        R::Order: Clone,
+    {
+        let v = rmq.values().as_slice();
+        let wm = WaveletMatrix::with_order(v.to_vec().into(), rmq.order().clone());
+        for i in 0..v.len() + 1 {
+            for j in i..v.len() + 1 {
+                let expected = sorted(v, rmq.order(), i, j);
+                for k in [0, 1, 3, j - i, j - i + 2] {
+                    let res: Vec<_> = top_k(rmq, idx(i)..idx(j), k)
+                        .iter()
+                        .map(|p| (p.idx(), p.val()))
+                        .collect();
+                    assert_eq!(expected[..usize::min(k, j - i)], res);
+                }
+                for (k, &(p, x)) in expected.iter().enumerate() {
+                    let Point(q, y) = wm.kth(idx(i)..idx(j), k);
+                    assert_eq!((p, x), (q, y));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_top_k_and_kth() {
+        let v: IdxVec<Lcp> = vec![3, 1, 4, 1, 5, 9, 2, 6, 5, 3, 5, 8, 9, 7, 9, 3].into();
+        check(&PowerRMQImpl::<Lcp, _>::from_slice(&v, Smallest));
+        check(&BlockRMQImpl::<Lcp, _>::from_slice(&v, Largest));
+        check(&NaiveRMQImpl::from_slice(&v, Rightmost(Smallest)));
+        check(&NaiveRMQImpl::from_slice(&v, Rightmost(Largest)));
+
+        let one: IdxVec<Lcp> = vec![7].into();
+        check(&PowerRMQImpl::<Lcp, _>::from_slice(&one, Smallest));
+        let empty: IdxVec<Lcp> = vec![].into();
+        check(&PowerRMQImpl::<Lcp, _>::from_slice(&empty, Smallest));
+
+        let wm = WaveletMatrix::new(v);
+        let Point(i, x) = wm.kth(idx(2)..idx(8), 0);
+        assert_eq!((idx(3), 1), (i, x));
+        assert_eq!(6, wm.kth(idx(2)..idx(8), 4).val());
+
+        // Enough values for the levels to span a few rank blocks
+        let v: Vec<u32> = (0..1500).map(|i| (i * 7919 + 5) % 97).collect();
+        let wm = WaveletMatrix::new(v.clone().into());
+        for (i, j) in [(0, 1500), (10, 11), (600, 1100), (1499, 1500)] {
+            let expected = sorted(&v, &Smallest, i, j);
+            for (k, &(p, x)) in expected.iter().enumerate() {
+                let Point(q, y) = wm.kth(idx(i)..idx(j), k);
+                assert_eq!((p, x), (q, y));
+            }
+        }
+    }
+}
@@ -0,0 +1,191 @@
+// Words per block in RankBits
+const BLOCK_WORDS: usize = 8;
+
+/// Bit vector with O(1) rank. We count the set bits before each block of
+/// BLOCK_WORDS words, which adds an eighth to the space, and popcount the
+/// rest of the block.
+pub(crate) struct RankBits {
+    bits: Vec<u64>,
+    // Number of set bits before each block, and in all of them if the
+    // last block is full, so rank works up to the end
+    ranks: Vec<usize>,
+}
+
+impl RankBits {
+    pub(crate) fn new(n: usize, bit: impl Fn(usize) -> bool) -> RankBits {
+        let mut bits = vec![0u64; n.div_ceil(64)];
+        for r in (0..n).filter(|&r| bit(r)) {
+            bits[r / 64] |= 1 << (r % 64);
+        }
+        let mut ranks = Vec::with_capacity(bits.len() / BLOCK_WORDS + 1);
+        let mut sum = 0;
+        for block in bits.chunks(BLOCK_WORDS) {
+            ranks.push(sum);
+            sum += block.iter().map(|w| w.count_ones() as usize).sum::<usize>();
+        }
+        if bits.len().is_multiple_of(BLOCK_WORDS) {
+            ranks.push(sum);
+        }
+        RankBits { bits, ranks }
+    }
+
+    #[inline]
+    pub(crate) fn get(&self, r: usize) -> bool {
+        self.bits[r / 64] >> (r % 64) & 1 == 1
+    }
+
+    // Number of set bits before r
+    #[inline]
+    pub(crate) fn rank(&self, r: usize) -> usize {
+        let (w, b) = (r / 64, r % 64);
+        let block = w / BLOCK_WORDS;
+        let words = &self.bits[block * BLOCK_WORDS..w];
+        let full: usize = words.iter().map(|w| w.count_ones() as usize).sum();
+        let below = if b == 0 {
+            0
+        } else {
+            (self.bits[w] & ((1u64 << b) - 1)).count_ones() as usize
+        };
+        self.ranks[block] + full + below
+    }
+}
+
+/// The bit levels of a wavelet matrix over a sequence of codes below
+/// 2^width. There is a RankBits for each bit of the codes, from the most
+/// significant down, and each level holds its bit for the codes in the
+/// order the bits above sorted them in, stably, zeros first. That is n
+/// bits per level plus an eighth for rank, and the queries take a rank or
+/// two per level.
+pub(crate) struct WaveletCodes {
+    levels: Vec<RankBits>,
+    // Number of zeros on each level
+    zeros: Vec<usize>,
+}
+
+impl WaveletCodes {
+    pub(crate) fn new(mut codes: Vec<usize>, width: u32) -> WaveletCodes {
+        let n = codes.len();
+        let mut levels = Vec::with_capacity(width as usize);
+        let mut zeros = Vec::with_capacity(width as usize);
+        let mut next = Vec::with_capacity(n);
+        for l in (0..width).rev() {
+            let one = |c: usize| c >> l & 1 == 1;
+            levels.push(RankBits::new(n, |i| one(codes[i])));
+            next.clear();
+            next.extend(codes.iter().filter(|&&c| !one(c)));
+            zeros.push(next.len());
+            next.extend(codes.iter().filter(|&&c| one(c)));
+            std::mem::swap(&mut codes, &mut next);
+        }
+        WaveletCodes { levels, zeros }
+    }
+
+    #[inline]
+    pub(crate) fn width(&self) -> usize {
+        self.levels.len()
+    }
+
+    // Follows positions s and i one level down, into the zeros or the
+    // ones, depending on bit.
+    #[inline]
+    fn step(&self, l: usize, bit: bool, s: usize, i: usize) -> (usize, usize) {
+        let bits = &self.levels[l];
+        if bit {
+            (self.zeros[l] + bits.rank(s), self.zeros[l] + bits.rank(i))
+        } else {
+            (s - bits.rank(s), i - bits.rank(i))
+        }
+    }
+
+    // Number of times code c occurs before position i
+    pub(crate) fn rank(&self, c: usize, i: usize) -> usize {
+        let width = self.width();
+        let (mut s, mut i) = (0, i);
+        for l in 0..width {
+            (s, i) = self.step(l, c >> (width - 1 - l) & 1 == 1, s, i);
+        }
+        i - s
+    }
+
+    // The code at position i, and the number of times it occurs before i
+    pub(crate) fn access_rank(&self, i: usize) -> (usize, usize) {
+        let (mut c, mut s, mut i) = (0, 0, i);
+        for l in 0..self.width() {
+            let bit = self.levels[l].get(i);
+            c = c << 1 | bit as usize;
+            (s, i) = self.step(l, bit, s, i);
+        }
+        (c, i - s)
+    }
+
+    // The k-th smallest code in positions [i,j), counting from zero. We
+    // follow the range into the zeros if it has more than k of them and
+    // into the ones otherwise, picking up the bits of the code on the way.
+    pub(crate) fn kth(&self, i: usize, j: usize, k: usize) -> usize {
+        let (mut i, mut j, mut k, mut c) = (i, j, k, 0);
+        for l in 0..self.width() {
+            let (oi, oj) = (self.levels[l].rank(i), self.levels[l].rank(j));
+            let zeros = (j - oj) - (i - oi);
+            c <<= 1;
+            if k < zeros {
+                (i, j) = (i - oi, j - oj);
+            } else {
+                k -= zeros;
+                c |= 1;
+                (i, j) = (self.zeros[l] + oi, self.zeros[l] + oj);
+            }
+        }
+        c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_bits() {
+        for n in [0, 1, 63, 64, 65, 511, 512, 513, 2000] {
+            let bit = |i: usize| i.is_multiple_of(3) || i % 7 == 1;
+            let bits = RankBits::new(n, bit);
+            let mut rank = 0;
+            for i in 0..=n {
+                assert_eq!(rank, bits.rank(i));
+                if i < n {
+                    assert_eq!(bit(i), bits.get(i));
+                    rank += bit(i) as usize;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_wavelet_codes() {
+        let codes: Vec<usize> = (0..700).map(|i| (i * i % 13 + i / 5) % 11).collect();
+        let wc = WaveletCodes::new(codes.clone(), 4);
+        assert_eq!(4, wc.width());
+        for i in 0..=codes.len() {
+            for c in 0..16 {
+                let expected = codes[..i].iter().filter(|&&d| d == c).count();
+                assert_eq!(expected, wc.rank(c, i));
+            }
+            if i < codes.len() {
+                let before = codes[..i].iter().filter(|&&d| d == codes[i]).count();
+                assert_eq!((codes[i], before), wc.access_rank(i));
+            }
+        }
+        for (i, j) in [(0, 700), (3, 4), (100, 180), (650, 700)] {
+            let mut sorted = codes[i..j].to_vec();
+            sorted.sort();
+            for (k, &c) in sorted.iter().enumerate() {
+                assert_eq!(c, wc.kth(i, j, k));
+            }
+        }
+
+        // With a single code, there is nothing to store
+        let wc = WaveletCodes::new(vec![0; 10], 0);
+        assert_eq!((0, 4), wc.access_rank(4));
+        assert_eq!(7, wc.rank(0, 7));
+        assert_eq!(0, wc.kth(2, 9, 3));
+    }
+}
@@ -32,6 +32,7 @@ apply_base_types!(base_trait);
 
 /// Wrapper type.
 #[derive(Clone, Copy, Debug)]
+#[repr(transparent)] // So a Val has the same layout as the value it wraps.
 pub struct Val<_Tag: TypeTrait>(pub _Tag::Type);
 
 // Expose the type trait directly from the Val wrapper